@@ -0,0 +1,84 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn desktop_entry_path() -> PathBuf {
+    let config_home = std::env::var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| {
+            let home = std::env::var("HOME").unwrap_or_default();
+            PathBuf::from(home).join(".config")
+        });
+
+    config_home.join("autostart").join("hyprsnow.desktop")
+}
+
+fn current_binary() -> Result<PathBuf, String> {
+    std::env::current_exe().map_err(|e| format!("failed to find the hyprsnow binary: {}", e))
+}
+
+/// `path` as an argument of a desktop entry's `Exec` key: quoted when it holds reserved
+/// characters, then escaped once more as a string value, and with `%` doubled so it isn't
+/// taken for a field code.
+fn exec_argument(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('%', "%%");
+    if !path.contains(|c: char| c.is_whitespace() || "\"'\\><~|&;$*?#()`".contains(c)) {
+        return path;
+    }
+
+    let mut quoted = String::from("\"");
+    for c in path.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted.replace('\\', "\\\\")
+}
+
+/// `path` quoted for the shell Hyprland hands `exec-once` lines to, where needed.
+fn shell_argument(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    if path
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "/._-+".contains(c))
+    {
+        return path.into_owned();
+    }
+    format!("'{}'", path.replace('\'', r"'\''"))
+}
+
+/// The line that starts hyprsnow with the session from `hyprland.conf`.
+pub fn exec_once_line() -> Result<String, String> {
+    let binary = current_binary()?;
+    Ok(format!("exec-once = {}", shell_argument(&binary)))
+}
+
+/// Writes an XDG autostart entry running this binary to `~/.config/autostart`, unless one
+/// exists already. Returns the path written.
+pub fn install() -> Result<PathBuf, String> {
+    let path = desktop_entry_path();
+    if path.exists() {
+        return Err(format!(
+            "{} already exists, leaving it alone",
+            path.display()
+        ));
+    }
+
+    let entry = format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=hyprsnow\n\
+         Comment=Snow overlay for Wayland/Hyprland\n\
+         Exec={}\n\
+         Terminal=false\n\
+         X-GNOME-Autostart-enabled=true\n",
+        exec_argument(&current_binary()?)
+    );
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("failed to create {}: {}", dir.display(), e))?;
+    }
+    fs::write(&path, entry).map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+    Ok(path)
+}
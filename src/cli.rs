@@ -1,13 +1,36 @@
-use clap::Parser;
+use crate::config::{
+    FlakeShape, LandOn, LayerMode, MeltCurve, OrphanBehavior, ParticleMode, RegionAnchor,
+    ScheduleSlot, ShellLayer, SuppressOn, parse_class_color, parse_color, parse_schedule_slot,
+    parse_snow_color,
+};
+use clap::{Parser, Subcommand};
+use iced::Color;
+use regex::Regex;
+use std::path::PathBuf;
 
 #[derive(Parser, Clone)]
 #[command(name = "hyprsnow")]
 #[command(about = "Snow overlay for Wayland/Hyprland")]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Particle preset: snow, rain or leaves; other options override its defaults
+    #[arg(long, value_enum)]
+    pub mode: Option<ParticleMode>,
+
     /// Snow intensity (1-10)
     #[arg(long, value_parser = clap::value_parser!(u8).range(1..=10))]
     pub intensity: Option<u8>,
 
+    /// Exact number of snowflakes, overriding intensity
+    #[arg(long)]
+    pub count: Option<usize>,
+
+    /// Snowflakes per million pixels of monitor area, overriding intensity
+    #[arg(long)]
+    pub density_per_megapixel: Option<f32>,
+
     /// Minimum snowflake size in pixels
     #[arg(long)]
     pub size_min: Option<f32>,
@@ -16,12 +39,12 @@ pub struct Args {
     #[arg(long)]
     pub size_max: Option<f32>,
 
-    /// Minimum fall speed in pixels/second
-    #[arg(long)]
+    /// Minimum fall speed in pixels/second (negative makes some flakes rise)
+    #[arg(long, allow_negative_numbers = true)]
     pub speed_min: Option<f32>,
 
     /// Maximum fall speed in pixels/second
-    #[arg(long)]
+    #[arg(long, allow_negative_numbers = true)]
     pub speed_max: Option<f32>,
 
     /// Horizontal drift intensity (0 = none, 30 = strong)
@@ -32,9 +55,276 @@ pub struct Args {
     #[arg(long)]
     pub max_opacity: Option<f32>,
 
+    /// Steady horizontal wind speed in pixels/second (negative blows left)
+    #[arg(long, alias = "wind", allow_negative_numbers = true)]
+    pub wind_x: Option<f32>,
+
+    /// Steady vertical wind speed in pixels/second (negative lofts small flakes upward)
+    #[arg(long, allow_negative_numbers = true)]
+    pub wind_y: Option<f32>,
+
+    /// Peak extra wind speed of occasional gusts in pixels/second (0 = no gusts)
+    #[arg(long)]
+    pub gust_strength: Option<f32>,
+
+    /// Maximum height of snow piled up at the screen bottom in pixels (0 = no piles)
+    #[arg(long)]
+    pub max_pile_height: Option<f32>,
+
+    /// Whether snow falls in front of windows, behind them, or both
+    #[arg(long, value_enum)]
+    pub layer_mode: Option<LayerMode>,
+
+    /// Seconds a landed snowflake of middling size takes to melt away
+    #[arg(long)]
+    pub melt_duration: Option<f32>,
+
+    /// How landed snowflakes fade while melting
+    #[arg(long, value_enum)]
+    pub melt_curve: Option<MeltCurve>,
+
+    /// Frame rate cap (10-144); motion speed is unaffected
+    #[arg(long, value_parser = clap::value_parser!(u32).range(10..=144))]
+    pub fps: Option<u32>,
+
+    /// Minimum spin of image snowflakes in degrees/second (negative spins counter-clockwise)
+    #[arg(long, allow_negative_numbers = true)]
+    pub spin_min: Option<f32>,
+
+    /// Maximum spin of image snowflakes in degrees/second
+    #[arg(long, allow_negative_numbers = true)]
+    pub spin_max: Option<f32>,
+
+    /// Hide the snow and idle at 1 fps while no windows are visible
+    #[arg(long, num_args(0..=1), default_missing_value = "true")]
+    pub idle_when_empty: Option<bool>,
+
+    /// Snow color as a hex value (e.g. #ffffff), or auto for the pywal theme's foreground
+    #[arg(long, value_parser = parse_snow_color)]
+    pub color: Option<Color>,
+
+    /// List of hex colors; each snowflake picks one at random, overriding --color
+    #[arg(long, num_args(1..), value_parser = parse_color)]
+    pub palette: Option<Vec<Color>>,
+
     /// Path to custom snowflake image
     /// If not provided, default circle shape will be used
     /// Make sure the image has a transparent background (e.g., PNG format)
     #[arg(long, num_args(1..))]
-    pub image_path: Option<Vec<String>>,
+    pub image_path: Option<Vec<PathBuf>>,
+
+    /// Regex patterns matched against window class and title; matching windows catch no snow
+    #[arg(long, num_args(1..))]
+    pub exclude: Option<Vec<String>>,
+
+    /// Snow colors by window class as pattern,color pairs, e.g. kitty,#7fd962; snow resting on a
+    /// matching window takes that color
+    #[arg(long, num_args(1..), value_parser = parse_class_color)]
+    pub class_colors: Option<Vec<(Regex, Color)>>,
+
+    /// Intensities by time of day as HH:MM-HH:MM:intensity slots, e.g. 18:00-23:00:8 23:00-06:00:3
+    #[arg(long, num_args(1..), value_parser = parse_schedule_slot)]
+    pub schedule: Option<Vec<ScheduleSlot>>,
+
+    /// Connector names of the monitors to show snow on (e.g. DP-2); all monitors by default
+    #[arg(long, num_args(1..))]
+    pub monitors: Option<Vec<String>>,
+
+    /// Downward acceleration in pixels/second², flakes speed up to a size-based terminal velocity (0 = constant speed)
+    #[arg(long)]
+    pub gravity: Option<f32>,
+
+    /// Melt speed factor for snow on the focused window (1 = same as elsewhere)
+    #[arg(long)]
+    pub focus_melt_multiplier: Option<f32>,
+
+    /// Pixels of fall over which new flakes fade in (0 = appear at once)
+    #[arg(long)]
+    pub fade_in_distance: Option<f32>,
+
+    /// Fake depth: bigger flakes fall faster and brighter, smaller ones slower and dimmer
+    #[arg(long, num_args(0..=1), default_missing_value = "true")]
+    pub parallax: Option<bool>,
+
+    /// Let snow settle on windows and the screen bottom; false keeps it falling through everything
+    #[arg(long, num_args(0..=1), default_missing_value = "true")]
+    pub land: Option<bool>,
+
+    /// Draw round snowflakes with soft, fading edges (also turns on antialiasing at startup)
+    #[arg(long, num_args(0..=1), default_missing_value = "true")]
+    pub soft_edges: Option<bool>,
+
+    /// Start with an empty screen and let the snow fall in from the top
+    #[arg(long, num_args(0..=1), default_missing_value = "true")]
+    pub startup_ramp: Option<bool>,
+
+    /// Match images to flake sizes: the largest images go to the largest flakes
+    #[arg(long, num_args(0..=1), default_missing_value = "true")]
+    pub image_size_map: Option<bool>,
+
+    /// Hide the snow on monitors showing a fullscreen window
+    #[arg(long, num_args(0..=1), default_missing_value = "true")]
+    pub hide_on_fullscreen: Option<bool>,
+
+    /// Window states that hide the snow with --hide-on-fullscreen: fullscreen, maximized or both
+    #[arg(long, num_args(1..), value_enum)]
+    pub suppress_on: Option<Vec<SuppressOn>>,
+
+    /// Gently push falling snowflakes away from the mouse pointer
+    #[arg(long, num_args(0..=1), default_missing_value = "true")]
+    pub cursor_interaction: Option<bool>,
+
+    /// How fast snowflakes sway side to side in radians/second, varied per flake
+    #[arg(long)]
+    pub drift_freq: Option<f32>,
+
+    /// Layer-shell layer to draw on: overlay, top, bottom or background
+    #[arg(long, value_enum)]
+    pub shell_layer: Option<ShellLayer>,
+
+    /// Random seed for a repeatable snowfall
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Pixels trimmed off each side of a window's top edge before snow can land on it
+    #[arg(long)]
+    pub land_margin: Option<f32>,
+
+    /// Seconds a flake may fall before it respawns at the top (0 = no limit)
+    #[arg(long)]
+    pub max_lifetime: Option<f32>,
+
+    /// Chance (0-1) that a flake spawns as a small cluster of flakes falling together
+    #[arg(long)]
+    pub clump_chance: Option<f32>,
+
+    /// Let snow land on layer-shell bars and docks such as Waybar
+    #[arg(long, num_args(0..=1), default_missing_value = "true")]
+    pub land_on_bars: Option<bool>,
+
+    /// Windows snow can land on: all, tiled or floating; the others let it fall through
+    #[arg(long, value_enum)]
+    pub land_on: Option<LandOn>,
+
+    /// Snow on a window that leaves for another workspace: fall off it, or fade out in place
+    #[arg(long, value_enum)]
+    pub orphan_behavior: Option<OrphanBehavior>,
+
+    /// Strength (0-1) of a faint tint darkening towards the screen edges (0 = off)
+    #[arg(long)]
+    pub tint_strength: Option<f32>,
+
+    /// Color of the edge tint as a hex value (e.g. #9ec8ff)
+    #[arg(long, value_parser = parse_color)]
+    pub tint_color: Option<Color>,
+
+    /// Let falling snowflakes shimmer by gently varying their brightness
+    #[arg(long, num_args(0..=1), default_missing_value = "true")]
+    pub twinkle: Option<bool>,
+
+    /// Snowflake shapes: circle, hexagon or star; each flake picks one of those given
+    #[arg(long, num_args(1..), value_enum)]
+    pub shape: Option<Vec<FlakeShape>>,
+
+    /// Show a small overlay with the frame rate, flake count and tracked windows and monitors
+    #[arg(long, num_args(0..=1), default_missing_value = "true")]
+    pub debug: Option<bool>,
+
+    /// Pixels on either side of the mouse pointer where no new flakes spawn (0 = anywhere)
+    #[arg(long)]
+    pub avoid_cursor_radius: Option<f32>,
+
+    /// How much flake size varies melt time (0-1); bigger flakes melt slower, smaller ones faster
+    #[arg(long)]
+    pub melt_size_variation: Option<f32>,
+
+    /// Show snow only on the monitor holding the focused window
+    #[arg(long, num_args(0..=1), default_missing_value = "true")]
+    pub active_monitor_only: Option<bool>,
+
+    /// Chance (0-1) that a flake reaching a window top settles there instead of falling past
+    #[arg(long)]
+    pub stick_chance: Option<f32>,
+
+    /// Where new flakes tend to spawn, from -1 (left edge) over 0 (evenly) to 1 (right edge)
+    #[arg(long, allow_negative_numbers = true)]
+    pub spawn_bias: Option<f32>,
+
+    /// Opacity (0-1) below which a melting flake is done and falls anew, before its melt time is up
+    #[arg(long)]
+    pub reset_opacity_threshold: Option<f32>,
+
+    /// How much behind-layer flakes slow down while passing behind a window (0-1, 0 = not at all)
+    #[arg(long)]
+    pub behind_slowdown: Option<f32>,
+
+    /// Strength (0-1) of fading trails behind falling snowflakes (0 = off)
+    #[arg(long)]
+    pub trail_strength: Option<f32>,
+
+    /// Keep each snowflake on the monitor it spawned over instead of drifting across monitor gaps
+    #[arg(long, num_args(0..=1), default_missing_value = "true")]
+    pub per_monitor_wrap: Option<bool>,
+
+    /// Height in pixels of a band along the --anchor edge that the snow stays in (0 = whole
+    /// screen)
+    #[arg(long)]
+    pub region: Option<u32>,
+
+    /// Screen edge the --region band runs along: top or bottom
+    #[arg(long, value_enum)]
+    pub anchor: Option<RegionAnchor>,
+
+    /// Render a single frame to this PNG file and exit instead of showing the overlay
+    #[arg(long)]
+    pub snapshot: Option<PathBuf>,
+
+    /// Width in pixels of the --snapshot image
+    #[arg(
+        long,
+        default_value_t = 1920,
+        requires = "snapshot",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    pub width: u32,
+
+    /// Height in pixels of the --snapshot image
+    #[arg(
+        long,
+        default_value_t = 1080,
+        requires = "snapshot",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    pub height: u32,
+
+    /// Seconds of snowfall simulated before the --snapshot frame is taken
+    #[arg(long, default_value_t = 10.0, requires = "snapshot")]
+    pub warmup: f32,
+
+    /// Stop an already running hyprsnow instance instead of exiting
+    #[arg(long)]
+    pub replace: bool,
+
+    /// Hold the keyboard so + and - change the intensity and space pauses; windows get no key
+    /// presses while hyprsnow runs
+    #[arg(long)]
+    pub interactive: bool,
+}
+
+#[derive(Subcommand, Clone)]
+pub enum Command {
+    /// Print the settings that would take effect, after the config file and the options given
+    /// here, then exit without starting the overlay
+    Config,
+    /// Write a commented config with every option at its default to
+    /// ~/.config/hypr/hyprsnow.conf, unless that file exists
+    Init,
+    /// Write an autostart entry running this binary to ~/.config/autostart/hyprsnow.desktop,
+    /// unless that file exists
+    InstallAutostart {
+        /// Print the exec-once line to add to hyprland.conf instead; Hyprland doesn't start
+        /// autostart entries on its own
+        #[arg(long)]
+        exec_once: bool,
+    },
 }
\ No newline at end of file
@@ -1,43 +1,895 @@
 use crate::cli::Args;
+use clap::{CommandFactory, ValueEnum};
+use hyprlang::{ConfigValue, SpecialCategoryDescriptor};
+use iced::Color;
 use notify::{Event, EventKind, RecursiveMode, Watcher};
-use std::path::PathBuf;
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc;
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::Duration;
+
+/// Which depth layer snowflakes fall in relative to windows
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LayerMode {
+    /// Flakes fall in front of windows and land on them
+    #[default]
+    Front,
+    /// Flakes fall behind windows, dimmed where a window covers them
+    Behind,
+    /// A mix of front and behind flakes
+    Both,
+}
+
+/// How a landed flake's opacity decays while it melts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum MeltCurve {
+    /// Fade out at a constant rate
+    #[default]
+    Linear,
+    /// Fade quickly at first, then linger faintly
+    #[value(alias = "ease_out")]
+    EaseOut,
+    /// Stay fully visible, then vanish at once
+    Instant,
+}
+
+/// Outline of a snowflake drawn without an image
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum FlakeShape {
+    /// A plain disc, the cheapest to draw
+    #[default]
+    Circle,
+    /// A flat hexagon, like a snow crystal seen from above
+    Hexagon,
+    /// A six-pointed star
+    Star,
+}
+
+/// Parses a comma-separated list of shapes, e.g. `hexagon, star`.
+pub fn parse_shapes(value: &str) -> Result<Vec<FlakeShape>, String> {
+    value
+        .split(',')
+        .filter(|shape| !shape.trim().is_empty())
+        .map(|shape| FlakeShape::from_str(shape.trim(), true))
+        .collect()
+}
+
+/// Window state that hides the snow on its monitor while `hide_on_fullscreen` is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SuppressOn {
+    /// A window covering the whole monitor, bars included
+    Fullscreen,
+    /// A window filling the monitor's work area, next to any bars
+    Maximized,
+}
+
+/// Parses a comma-separated list of window states, e.g. `fullscreen, maximized`.
+pub fn parse_suppress_on(value: &str) -> Result<Vec<SuppressOn>, String> {
+    value
+        .split(',')
+        .filter(|state| !state.trim().is_empty())
+        .map(|state| SuppressOn::from_str(state.trim(), true))
+        .collect()
+}
+
+/// Layer-shell layer the overlay surface lives on, from above everything to the wallpaper
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ShellLayer {
+    /// Above all windows, including fullscreen ones
+    #[default]
+    Overlay,
+    /// Above regular windows, below fullscreen ones
+    Top,
+    /// Below windows, above the wallpaper
+    Bottom,
+    /// Level with the wallpaper
+    Background,
+}
+
+/// Which windows snow can land on, by how Hyprland lays them out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LandOn {
+    /// Every window
+    #[default]
+    All,
+    /// Only windows placed by the tiling layout
+    Tiled,
+    /// Only floating windows
+    Floating,
+}
+
+/// What happens to snow resting on a window that leaves the screen without being closed, e.g.
+/// when switching workspaces
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OrphanBehavior {
+    /// The snow falls off, as it does from a closed window
+    #[default]
+    Fall,
+    /// The snow stays where it was and fades out
+    Fade,
+}
+
+/// Screen edge a partial overlay with `region` hugs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum RegionAnchor {
+    /// A band along the top edge, where the snow falls in
+    #[default]
+    Top,
+    /// A band along the bottom edge, where the snow settles
+    Bottom,
+}
+
+/// What kind of particle falls; each mode comes with its own set of defaults
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ParticleMode {
+    /// Round snowflakes that drift, pile up and melt
+    #[default]
+    Snow,
+    /// Fast, thin raindrops falling nearly straight down
+    Rain,
+    /// Slow, tumbling autumn leaves
+    Leaves,
+}
+
+/// A daily time window with its own intensity, e.g. `18:00-23:00:8`; times are in minutes
+/// after local midnight, and a window may run past midnight
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduleSlot {
+    pub start: u32,
+    pub end: u32,
+    pub intensity: u8,
+}
+
+impl ScheduleSlot {
+    /// Whether the slot covers this minute of the day.
+    pub fn contains(&self, minute: u32) -> bool {
+        if self.start <= self.end {
+            minute >= self.start && minute < self.end
+        } else {
+            minute >= self.start || minute < self.end
+        }
+    }
+}
+
+impl fmt::Display for ScheduleSlot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:02}:{:02}-{:02}:{:02}:{}",
+            self.start / 60,
+            self.start % 60,
+            self.end / 60,
+            self.end % 60,
+            self.intensity
+        )
+    }
+}
+
+/// Parses a single `HH:MM-HH:MM:intensity` schedule slot.
+pub fn parse_schedule_slot(value: &str) -> Result<ScheduleSlot, String> {
+    fn time(value: &str) -> Option<u32> {
+        let (hours, minutes) = value.trim().split_once(':')?;
+        let (hours, minutes): (u32, u32) = (hours.parse().ok()?, minutes.parse().ok()?);
+        (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+    }
+
+    let invalid = || format!("expected a slot like 18:00-23:00:8, got {:?}", value.trim());
+    let (range, intensity) = value.rsplit_once(':').ok_or_else(invalid)?;
+    let (start, end) = range.split_once('-').ok_or_else(invalid)?;
+    let intensity: u8 = intensity.trim().parse().map_err(|_| invalid())?;
+
+    Ok(ScheduleSlot {
+        start: time(start).ok_or_else(invalid)?,
+        end: time(end).ok_or_else(invalid)?,
+        intensity: intensity.clamp(1, 10),
+    })
+}
+
+/// Parses a comma-separated schedule such as `18:00-23:00:8, 23:00-06:00:3`.
+pub fn parse_schedule(value: &str) -> Result<Vec<ScheduleSlot>, String> {
+    value
+        .trim()
+        .trim_matches('"')
+        .split(',')
+        .filter(|slot| !slot.trim().is_empty())
+        .map(parse_schedule_slot)
+        .collect()
+}
 
 #[derive(Debug, Clone)]
 pub struct SnowConfig {
+    pub mode: ParticleMode,
     pub intensity: u8,
+    pub count: Option<usize>,
+    /// Flakes per million pixels of monitor area, replacing `intensity` so the snow looks
+    /// equally dense at any resolution; an explicit `count` still wins
+    pub density_per_megapixel: Option<f32>,
     pub size_min: f32,
     pub size_max: f32,
     pub speed_min: f32,
     pub speed_max: f32,
     pub drift: f32,
     pub max_opacity: f32,
-    pub image_paths: Option<Vec<String>>,
+    /// Steady wind in pixels/second; positive x blows right, positive y blows down
+    pub wind_x: f32,
+    pub wind_y: f32,
+    pub gust_strength: f32,
+    pub max_pile_height: f32,
+    pub layer_mode: LayerMode,
+    pub melt_duration: f32,
+    pub melt_curve: MeltCurve,
+    pub fps: u32,
+    /// Sprite spin range in degrees/second
+    pub spin_min: f32,
+    pub spin_max: f32,
+    pub idle_when_empty: bool,
+    pub color: Color,
+    /// Tints picked at random per flake; `color` is used when empty
+    pub palette: Vec<Color>,
+    /// Downward acceleration in pixels/second²; 0 keeps every flake at its constant speed
+    pub gravity: f32,
+    /// Melt speed factor for snow resting on the focused window
+    pub focus_melt_multiplier: f32,
+    /// Pixels of fall over which a new flake fades in from transparent
+    pub fade_in_distance: f32,
+    /// Derive speed and opacity from size so bigger flakes look nearer
+    pub parallax: bool,
+    /// Whether flakes settle on windows and the ground at all
+    pub land: bool,
+    /// Draw round flakes with a soft, fading edge instead of a hard one
+    pub soft_edges: bool,
+    /// Start new flakes above the screen so snow builds up instead of appearing everywhere at once
+    pub startup_ramp: bool,
+    /// Give bigger flakes the bigger images instead of picking images at random
+    pub image_size_map: bool,
+    /// Keep snow off monitors showing a window in one of the `suppress_on` states
+    pub hide_on_fullscreen: bool,
+    /// Window states that count as fullscreen for `hide_on_fullscreen`
+    pub suppress_on: Vec<SuppressOn>,
+    /// Push falling flakes away from the mouse pointer
+    pub cursor_interaction: bool,
+    /// How fast flakes sway from side to side, in radians/second (varied ±25% per flake)
+    pub drift_freq: f32,
+    /// Only read at startup; the surface can't move between layers afterwards
+    pub shell_layer: ShellLayer,
+    /// Fixed random seed for a repeatable snowfall; random on every start when unset
+    pub seed: Option<u64>,
+    /// Pixels inset from each side of a window's top edge where snow slides off instead of landing
+    pub land_margin: f32,
+    /// Seconds a flake may keep falling before it respawns at the top; 0 disables the limit
+    pub max_lifetime: f32,
+    /// Chance (0-1) that a new flake is a cluster of a few flakes falling together
+    pub clump_chance: f32,
+    /// Treat layer-shell bars and docks like windows that snow can land on
+    pub land_on_bars: bool,
+    /// Whether snow lands on tiled windows, floating ones or both
+    pub land_on: LandOn,
+    /// Whether snow on a window that went to another workspace falls or fades out in place
+    pub orphan_behavior: OrphanBehavior,
+    /// Strength (0-1) of a faint vignette in `tint_color` along the monitor edges; 0 turns it off
+    pub tint_strength: f32,
+    pub tint_color: Color,
+    /// Let falling flakes shimmer by gently varying their opacity
+    pub twinkle: bool,
+    /// Shapes picked at random per flake in snow mode
+    pub shapes: Vec<FlakeShape>,
+    /// Show a corner overlay with performance numbers
+    pub debug: bool,
+    /// Half width of the column around the pointer that new flakes avoid; 0 turns it off
+    pub avoid_cursor_radius: f32,
+    /// Spread of melt times by size: the largest flakes take `melt_duration` × (1 + this), the
+    /// smallest `melt_duration` × (1 - this)
+    pub melt_size_variation: f32,
+    /// Show snow only on the monitor with the focused window, following focus
+    pub active_monitor_only: bool,
+    /// Chance (0-1) that a flake settles on a window top it reaches instead of falling past it
+    pub stick_chance: f32,
+    /// Skews spawn columns towards the left (-1) or right (1) edge of each monitor; 0 spreads
+    /// them evenly
+    pub spawn_bias: f32,
+    /// Opacity below which a melting flake counts as gone, ending its melt early
+    pub reset_opacity_threshold: f32,
+    /// Fraction of their fall speed behind-layer flakes lose while behind a window
+    pub behind_slowdown: f32,
+    /// Opacity (0-1) of the fading copies trailing falling flakes; 0 draws none
+    pub trail_strength: f32,
+    /// Keep flakes on the monitor they spawned over, wrapping at its edges instead of the
+    /// overlay's
+    pub per_monitor_wrap: bool,
+    /// Height in pixels of the band along `anchor` that the overlay covers; 0 covers the whole
+    /// screen
+    pub region: u32,
+    /// Screen edge the `region` band runs along
+    pub anchor: RegionAnchor,
+    pub image_paths: Vec<PathBuf>,
+    /// Intensities for times of day; `intensity` applies outside every slot
+    pub schedule: Vec<ScheduleSlot>,
+    /// Windows whose class or title matches any of these never catch snow
+    pub exclude: Vec<Regex>,
+    /// Colors for snow resting on windows whose class matches the pattern; the first match wins
+    pub class_colors: Vec<(Regex, Color)>,
+    /// Connector names of the monitors to show snow on; every monitor when empty
+    pub monitors: Vec<String>,
+    /// Intensity overrides keyed by monitor connector name (e.g. `DP-1`)
+    pub monitor_intensity: HashMap<String, u8>,
 }
 
 #[derive(Debug, Clone)]
 pub enum ConfigEvent {
-    ConfigChanged(SnowConfig),
+    ConfigChanged(Box<SnowConfig>),
+    /// The edited file doesn't parse; the running config should stay as it is
+    ParseError(String),
 }
 
 impl Default for SnowConfig {
     fn default() -> Self {
         Self {
+            mode: ParticleMode::Snow,
             intensity: 3,
+            count: None,
+            density_per_megapixel: None,
             size_min: 2.0,
             size_max: 5.0,
             speed_min: 30.0,
             speed_max: 80.0,
             drift: 20.0,
             max_opacity: 1.0,
-            image_paths: None,
+            wind_x: 0.0,
+            wind_y: 0.0,
+            gust_strength: 0.0,
+            max_pile_height: 40.0,
+            layer_mode: LayerMode::Front,
+            melt_duration: 4.0,
+            melt_curve: MeltCurve::Linear,
+            fps: 60,
+            spin_min: -45.0,
+            spin_max: 45.0,
+            idle_when_empty: false,
+            color: Color::WHITE,
+            palette: Vec::new(),
+            gravity: 0.0,
+            focus_melt_multiplier: 1.0,
+            fade_in_distance: 20.0,
+            parallax: false,
+            land: true,
+            soft_edges: false,
+            startup_ramp: false,
+            image_size_map: false,
+            hide_on_fullscreen: true,
+            suppress_on: vec![SuppressOn::Fullscreen],
+            cursor_interaction: false,
+            drift_freq: 1.0,
+            shell_layer: ShellLayer::Overlay,
+            seed: None,
+            land_margin: 0.0,
+            max_lifetime: 0.0,
+            clump_chance: 0.0,
+            land_on_bars: false,
+            land_on: LandOn::All,
+            orphan_behavior: OrphanBehavior::Fall,
+            tint_strength: 0.0,
+            tint_color: Color::from_rgb8(0x9e, 0xc8, 0xff),
+            twinkle: false,
+            shapes: vec![FlakeShape::Circle],
+            debug: false,
+            avoid_cursor_radius: 0.0,
+            melt_size_variation: 0.5,
+            active_monitor_only: false,
+            stick_chance: 1.0,
+            spawn_bias: 0.0,
+            reset_opacity_threshold: 0.01,
+            behind_slowdown: 0.0,
+            trail_strength: 0.0,
+            per_monitor_wrap: false,
+            region: 0,
+            anchor: RegionAnchor::Top,
+            image_paths: Vec::new(),
+            schedule: Vec::new(),
+            exclude: Vec::new(),
+            class_colors: Vec::new(),
+            monitors: Vec::new(),
+            monitor_intensity: HashMap::new(),
         }
     }
 }
 
-pub fn get_config_path() -> Option<PathBuf> {
+impl SnowConfig {
+    /// Defaults for a particle mode; anything set in the config file or on the command line
+    /// still takes precedence.
+    pub fn for_mode(mode: ParticleMode) -> Self {
+        let snow = Self::default();
+        match mode {
+            ParticleMode::Snow => snow,
+            ParticleMode::Rain => Self {
+                mode,
+                size_min: 1.0,
+                size_max: 2.0,
+                speed_min: 500.0,
+                speed_max: 800.0,
+                drift: 1.0,
+                max_opacity: 0.6,
+                max_pile_height: 0.0,
+                melt_duration: 0.3,
+                spin_min: 0.0,
+                spin_max: 0.0,
+                color: Color::from_rgb8(0xa8, 0xc0, 0xd8),
+                ..snow
+            },
+            ParticleMode::Leaves => Self {
+                mode,
+                intensity: 1,
+                size_min: 5.0,
+                size_max: 9.0,
+                speed_min: 25.0,
+                speed_max: 60.0,
+                drift: 60.0,
+                max_pile_height: 0.0,
+                melt_duration: 8.0,
+                spin_min: -120.0,
+                spin_max: 120.0,
+                palette: vec![
+                    Color::from_rgb8(0xc0, 0x4b, 0x1e),
+                    Color::from_rgb8(0xd9, 0x7b, 0x29),
+                    Color::from_rgb8(0xe8, 0xb0, 0x3a),
+                    Color::from_rgb8(0x8a, 0x4b, 0x22),
+                ],
+                ..snow
+            },
+        }
+    }
+
+    /// Number of snowflakes to simulate at `intensity`; an explicit `count` wins over it.
+    pub fn flake_count(&self, intensity: u8) -> usize {
+        self.count.unwrap_or(intensity as usize * 50)
+    }
+
+    /// Intensity the schedule asks for at this minute of the day, if any slot covers it.
+    pub fn scheduled_intensity(&self, minute: u32) -> Option<u8> {
+        self.schedule
+            .iter()
+            .find(|slot| slot.contains(minute))
+            .map(|slot| slot.intensity)
+    }
+
+    /// Whether snow should pass through a window with this class and title.
+    pub fn is_excluded(&self, class: &str, title: &str) -> bool {
+        self.exclude
+            .iter()
+            .any(|re| re.is_match(class) || re.is_match(title))
+    }
+
+    /// Whether `land_on` lets snow land on a window that is floating or not.
+    pub fn lands_on(&self, floating: bool) -> bool {
+        match self.land_on {
+            LandOn::All => true,
+            LandOn::Tiled => !floating,
+            LandOn::Floating => floating,
+        }
+    }
+
+    /// Color for snow resting on a window of this class, if `class_colors` gives it one.
+    pub fn class_color(&self, class: &str) -> Option<Color> {
+        self.class_colors
+            .iter()
+            .find(|(re, _)| re.is_match(class))
+            .map(|&(_, color)| color)
+    }
+
+    /// Sets a single `general` option from its textual value, applying the same limits as the
+    /// config file.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        fn num<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+            value
+                .parse()
+                .map_err(|_| format!("invalid number {:?}", value))
+        }
+
+        fn flag(value: &str) -> Result<bool, String> {
+            match value {
+                "true" | "yes" | "on" | "1" => Ok(true),
+                "false" | "no" | "off" | "0" => Ok(false),
+                _ => Err(format!("invalid boolean {:?}", value)),
+            }
+        }
+
+        match key {
+            "intensity" => self.intensity = num::<i64>(value)?.clamp(1, 10) as u8,
+            "count" => self.count = Some(num::<i64>(value)?.max(0) as usize),
+            "density_per_megapixel" => {
+                self.density_per_megapixel = Some(num::<f32>(value)?.max(0.0))
+            }
+            "size_min" => self.size_min = num(value)?,
+            "size_max" => self.size_max = num(value)?,
+            "speed_min" => self.speed_min = num(value)?,
+            "speed_max" => self.speed_max = num(value)?,
+            "drift" => self.drift = num(value)?,
+            "max_opacity" => self.max_opacity = num::<f32>(value)?.clamp(0.0, 1.0),
+            "wind" | "wind_x" => self.wind_x = num(value)?,
+            "wind_y" => self.wind_y = num(value)?,
+            "gust_strength" => self.gust_strength = num::<f32>(value)?.max(0.0),
+            "max_pile_height" => self.max_pile_height = num::<f32>(value)?.max(0.0),
+            "layer_mode" => self.layer_mode = LayerMode::from_str(value, true)?,
+            "melt_duration" => self.melt_duration = num::<f32>(value)?.max(0.1),
+            "melt_curve" => self.melt_curve = MeltCurve::from_str(value, true)?,
+            "fps" => self.fps = num::<i64>(value)?.clamp(10, 144) as u32,
+            "spin_min" => self.spin_min = num(value)?,
+            "spin_max" => self.spin_max = num(value)?,
+            "idle_when_empty" => self.idle_when_empty = flag(value)?,
+            "color" => self.color = parse_snow_color(value)?,
+            "gravity" => self.gravity = num::<f32>(value)?.max(0.0),
+            "focus_melt_multiplier" => self.focus_melt_multiplier = num::<f32>(value)?.max(0.0),
+            "fade_in_distance" => self.fade_in_distance = num::<f32>(value)?.max(0.0),
+            "parallax" => self.parallax = flag(value)?,
+            "land" => self.land = flag(value)?,
+            "soft_edges" => self.soft_edges = flag(value)?,
+            "startup_ramp" => self.startup_ramp = flag(value)?,
+            "image_size_map" => self.image_size_map = flag(value)?,
+            "hide_on_fullscreen" => self.hide_on_fullscreen = flag(value)?,
+            "suppress_on" => self.suppress_on = parse_suppress_on(value)?,
+            "cursor_interaction" => self.cursor_interaction = flag(value)?,
+            "drift_freq" => self.drift_freq = num::<f32>(value)?.max(0.0),
+            "land_margin" => self.land_margin = num::<f32>(value)?.max(0.0),
+            "max_lifetime" => self.max_lifetime = num::<f32>(value)?.max(0.0),
+            "clump_chance" => self.clump_chance = num::<f32>(value)?.clamp(0.0, 1.0),
+            "land_on_bars" => self.land_on_bars = flag(value)?,
+            "land_on" => self.land_on = LandOn::from_str(value, true)?,
+            "orphan_behavior" => self.orphan_behavior = OrphanBehavior::from_str(value, true)?,
+            "tint_strength" => self.tint_strength = num::<f32>(value)?.clamp(0.0, 1.0),
+            "tint_color" => self.tint_color = parse_color(value)?,
+            "schedule" => self.schedule = parse_schedule(value)?,
+            "twinkle" => self.twinkle = flag(value)?,
+            "shape" => self.shapes = parse_shapes(value)?,
+            "debug" => self.debug = flag(value)?,
+            "avoid_cursor_radius" => self.avoid_cursor_radius = num::<f32>(value)?.max(0.0),
+            "melt_size_variation" => self.melt_size_variation = num::<f32>(value)?.clamp(0.0, 1.0),
+            "active_monitor_only" => self.active_monitor_only = flag(value)?,
+            "stick_chance" => self.stick_chance = num::<f32>(value)?.clamp(0.0, 1.0),
+            "spawn_bias" => self.spawn_bias = num::<f32>(value)?.clamp(-1.0, 1.0),
+            "reset_opacity_threshold" => {
+                self.reset_opacity_threshold = num::<f32>(value)?.clamp(0.0, 1.0)
+            }
+            "behind_slowdown" => self.behind_slowdown = num::<f32>(value)?.clamp(0.0, 1.0),
+            "trail_strength" => self.trail_strength = num::<f32>(value)?.clamp(0.0, 1.0),
+            "per_monitor_wrap" => self.per_monitor_wrap = flag(value)?,
+            "region" => self.region = num::<i64>(value)?.max(0) as u32,
+            "anchor" => self.anchor = RegionAnchor::from_str(value, true)?,
+            _ => return Err(format!("unknown option {:?}", key)),
+        }
+        Ok(())
+    }
+
+    /// Swaps `*_min`/`*_max` pairs given the wrong way round and raises sizes that aren't
+    /// positive, with a warning, so a config mistake can't leave an empty range to pick sizes
+    /// or speeds from, or flakes without a size.
+    pub fn fix_ranges(&mut self) {
+        for (name, size) in [
+            ("size_min", &mut self.size_min),
+            ("size_max", &mut self.size_max),
+        ] {
+            if size.is_nan() || *size < MIN_FLAKE_SIZE {
+                eprintln!(
+                    "hyprsnow: {} ({}) must be positive, using {}",
+                    name, size, MIN_FLAKE_SIZE
+                );
+                *size = MIN_FLAKE_SIZE;
+            }
+        }
+
+        fn order(name: &str, min: &mut f32, max: &mut f32) {
+            if *min > *max {
+                eprintln!(
+                    "hyprsnow: {}_min ({}) is above {}_max ({}), swapping them",
+                    name, min, name, max
+                );
+                std::mem::swap(min, max);
+            }
+        }
+
+        order("size", &mut self.size_min, &mut self.size_max);
+        order("speed", &mut self.speed_min, &mut self.speed_max);
+    }
+
+    /// Whether snow may show on the monitor with the given connector name.
+    pub fn is_monitor_enabled(&self, name: &str) -> bool {
+        self.monitors.is_empty() || self.monitors.iter().any(|m| m == name)
+    }
+
+    /// Intensity for the monitor with the given connector name, falling back to `fallback`,
+    /// the intensity in effect for monitors without a block.
+    pub fn intensity_for_monitor(&self, name: &str, fallback: u8) -> u8 {
+        self.monitor_intensity
+            .get(name)
+            .copied()
+            .unwrap_or(fallback)
+    }
+}
+
+/// Smallest flake size `fix_ranges` lets through
+const MIN_FLAKE_SIZE: f32 = 0.1;
+
+/// Names accepted by `general:preset`
+const PRESETS: [&str; 4] = ["gentle", "flurry", "blizzard", "rain"];
+
+/// Tunes intensity, speed, drift and wind to a named look. Presets replace defaults only, so
+/// options set in the config file or on the command line still win; `rain` also switches to
+/// the rain mode unless a mode is given explicitly.
+pub fn apply_preset(config: &mut SnowConfig, name: &str) -> Result<(), String> {
+    match name.trim().to_ascii_lowercase().as_str() {
+        "gentle" => {
+            config.intensity = 2;
+            config.speed_min = 20.0;
+            config.speed_max = 45.0;
+            config.drift = 10.0;
+            config.wind_x = 0.0;
+            config.gust_strength = 0.0;
+        }
+        "flurry" => {
+            config.intensity = 5;
+            config.speed_min = 40.0;
+            config.speed_max = 110.0;
+            config.drift = 35.0;
+            config.wind_x = 15.0;
+            config.gust_strength = 30.0;
+        }
+        "blizzard" => {
+            config.intensity = 10;
+            config.speed_min = 120.0;
+            config.speed_max = 260.0;
+            config.drift = 40.0;
+            config.wind_x = 180.0;
+            config.gust_strength = 120.0;
+        }
+        "rain" => {
+            *config = SnowConfig {
+                intensity: 6,
+                wind_x: 20.0,
+                gust_strength: 15.0,
+                ..SnowConfig::for_mode(ParticleMode::Rain)
+            };
+        }
+        _ => {
+            return Err(format!(
+                "unknown preset {:?}, expected one of {}",
+                name,
+                PRESETS.join(", ")
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Prints the effective settings as `category:key = value` lines, using the config file's keys.
+impl fmt::Display for SnowConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn name(value: impl ValueEnum) -> String {
+            value
+                .to_possible_value()
+                .map(|v| v.get_name().to_string())
+                .unwrap_or_default()
+        }
+
+        writeln!(f, "general:mode = {}", name(self.mode))?;
+        writeln!(f, "general:intensity = {}", self.intensity)?;
+        if let Some(count) = self.count {
+            writeln!(f, "general:count = {}", count)?;
+        }
+        if let Some(density) = self.density_per_megapixel {
+            writeln!(f, "general:density_per_megapixel = {}", density)?;
+        }
+        writeln!(f, "general:size_min = {}", self.size_min)?;
+        writeln!(f, "general:size_max = {}", self.size_max)?;
+        writeln!(f, "general:speed_min = {}", self.speed_min)?;
+        writeln!(f, "general:speed_max = {}", self.speed_max)?;
+        writeln!(f, "general:drift = {}", self.drift)?;
+        writeln!(f, "general:max_opacity = {}", self.max_opacity)?;
+        writeln!(f, "general:wind_x = {}", self.wind_x)?;
+        writeln!(f, "general:wind_y = {}", self.wind_y)?;
+        writeln!(f, "general:gust_strength = {}", self.gust_strength)?;
+        writeln!(f, "general:max_pile_height = {}", self.max_pile_height)?;
+        writeln!(f, "general:layer_mode = {}", name(self.layer_mode))?;
+        writeln!(f, "general:melt_duration = {}", self.melt_duration)?;
+        writeln!(f, "general:melt_curve = {}", name(self.melt_curve))?;
+        writeln!(f, "general:fps = {}", self.fps)?;
+        writeln!(f, "general:spin_min = {}", self.spin_min)?;
+        writeln!(f, "general:spin_max = {}", self.spin_max)?;
+        writeln!(f, "general:idle_when_empty = {}", self.idle_when_empty)?;
+        writeln!(f, "general:color = {}", format_color(self.color))?;
+        for color in &self.palette {
+            writeln!(f, "general:palette = {}", format_color(*color))?;
+        }
+        writeln!(f, "general:gravity = {}", self.gravity)?;
+        writeln!(
+            f,
+            "general:focus_melt_multiplier = {}",
+            self.focus_melt_multiplier
+        )?;
+        writeln!(f, "general:fade_in_distance = {}", self.fade_in_distance)?;
+        writeln!(f, "general:parallax = {}", self.parallax)?;
+        writeln!(f, "general:land = {}", self.land)?;
+        writeln!(f, "general:soft_edges = {}", self.soft_edges)?;
+        writeln!(f, "general:startup_ramp = {}", self.startup_ramp)?;
+        writeln!(f, "general:image_size_map = {}", self.image_size_map)?;
+        writeln!(
+            f,
+            "general:hide_on_fullscreen = {}",
+            self.hide_on_fullscreen
+        )?;
+        let suppress_on: Vec<String> = self.suppress_on.iter().map(|s| name(*s)).collect();
+        writeln!(f, "general:suppress_on = {}", suppress_on.join(", "))?;
+        writeln!(
+            f,
+            "general:cursor_interaction = {}",
+            self.cursor_interaction
+        )?;
+        writeln!(f, "general:drift_freq = {}", self.drift_freq)?;
+        writeln!(f, "general:shell_layer = {}", name(self.shell_layer))?;
+        if let Some(seed) = self.seed {
+            writeln!(f, "general:seed = {}", seed)?;
+        }
+        writeln!(f, "general:land_margin = {}", self.land_margin)?;
+        writeln!(f, "general:max_lifetime = {}", self.max_lifetime)?;
+        writeln!(f, "general:clump_chance = {}", self.clump_chance)?;
+        writeln!(f, "general:land_on_bars = {}", self.land_on_bars)?;
+        writeln!(f, "general:land_on = {}", name(self.land_on))?;
+        writeln!(f, "general:orphan_behavior = {}", name(self.orphan_behavior))?;
+        writeln!(f, "general:tint_strength = {}", self.tint_strength)?;
+        writeln!(f, "general:tint_color = {}", format_color(self.tint_color))?;
+        writeln!(f, "general:twinkle = {}", self.twinkle)?;
+        let shapes: Vec<String> = self.shapes.iter().map(|s| name(*s)).collect();
+        writeln!(f, "general:shape = {}", shapes.join(", "))?;
+        writeln!(f, "general:debug = {}", self.debug)?;
+        writeln!(f, "general:avoid_cursor_radius = {}", self.avoid_cursor_radius)?;
+        writeln!(f, "general:melt_size_variation = {}", self.melt_size_variation)?;
+        writeln!(f, "general:active_monitor_only = {}", self.active_monitor_only)?;
+        writeln!(f, "general:stick_chance = {}", self.stick_chance)?;
+        writeln!(f, "general:spawn_bias = {}", self.spawn_bias)?;
+        writeln!(f, "general:reset_opacity_threshold = {}", self.reset_opacity_threshold)?;
+        writeln!(f, "general:behind_slowdown = {}", self.behind_slowdown)?;
+        writeln!(f, "general:trail_strength = {}", self.trail_strength)?;
+        writeln!(f, "general:per_monitor_wrap = {}", self.per_monitor_wrap)?;
+        writeln!(f, "general:region = {}", self.region)?;
+        writeln!(f, "general:anchor = {}", name(self.anchor))?;
+        for path in &self.image_paths {
+            writeln!(f, "general:image_path = {}", path.display())?;
+        }
+        if !self.schedule.is_empty() {
+            let slots: Vec<String> = self.schedule.iter().map(|s| s.to_string()).collect();
+            writeln!(f, "general:schedule = {}", slots.join(", "))?;
+        }
+        for pattern in &self.exclude {
+            writeln!(f, "general:exclude = {}", pattern.as_str())?;
+        }
+        for (pattern, color) in &self.class_colors {
+            writeln!(
+                f,
+                "general:class_colors = {}, {}",
+                pattern.as_str(),
+                format_color(*color)
+            )?;
+        }
+        for monitor in &self.monitors {
+            writeln!(f, "general:monitors = {}", monitor)?;
+        }
+
+        let mut monitors: Vec<_> = self.monitor_intensity.iter().collect();
+        monitors.sort();
+        for (monitor, intensity) in monitors {
+            writeln!(f, "monitor[{}]:intensity = {}", monitor, intensity)?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats a color as `#rrggbb`, the form `parse_color` reads back.
+fn format_color(color: Color) -> String {
+    let [r, g, b, _] = color.into_rgba8();
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// Parses a `#rrggbb`, `rrggbb` or `0xrrggbb` hex color.
+pub fn parse_color(value: &str) -> Result<Color, String> {
+    let hex = value.trim().trim_matches('"');
+    let hex = hex
+        .strip_prefix('#')
+        .or_else(|| hex.strip_prefix("0x"))
+        .unwrap_or(hex);
+
+    if hex.len() != 6 {
+        return Err(format!(
+            "expected a hex color like #aaccff, got {:?}",
+            value
+        ));
+    }
+
+    u32::from_str_radix(hex, 16)
+        .map(|rgb| Color::from_rgb8((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8))
+        .map_err(|_| format!("expected a hex color like #aaccff, got {:?}", value))
+}
+
+/// Parses a `class_colors` entry: a regex for the window class, then the color of snow resting
+/// on matching windows, e.g. `kitty, 0x7fd962`.
+pub fn parse_class_color(value: &str) -> Result<(Regex, Color), String> {
+    let value = value.trim().trim_matches('"');
+    let (pattern, color) = value.rsplit_once(',').ok_or_else(|| {
+        format!(
+            "expected a class pattern and a color like kitty, 0x7fd962, got {:?}",
+            value
+        )
+    })?;
+    let pattern = pattern.trim();
+    let pattern =
+        Regex::new(pattern).map_err(|e| format!("invalid class pattern {:?}: {}", pattern, e))?;
+    Ok((pattern, parse_color(color)?))
+}
+
+/// Reads a color written either as a quoted hex string or in hyprlang's own color syntax.
+fn get_color(config: &hyprlang::Config, key: &str) -> Option<Color> {
+    match config.get(key).ok()? {
+        ConfigValue::Color(c) => Some(Color::from_rgb8(c.r, c.g, c.b)),
+        ConfigValue::String(s) => parse_color(s)
+            .inspect_err(|e| eprintln!("hyprsnow: Invalid {}: {}", key, e))
+            .ok(),
+        _ => None,
+    }
+}
+
+/// Like `parse_color`, but also takes `auto` for the foreground color of the pywal theme.
+pub fn parse_snow_color(value: &str) -> Result<Color, String> {
+    if value.trim().trim_matches('"') == "auto" {
+        Ok(theme_color())
+    } else {
+        parse_color(value)
+    }
+}
+
+/// pywal's generated color scheme, which `color = auto` follows
+fn wal_colors_path() -> PathBuf {
+    let cache_home = std::env::var("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| expand_home("~/.cache"));
+    cache_home.join("wal").join("colors.json")
+}
+
+/// The foreground color of the current pywal theme, or white when there is none.
+fn theme_color() -> Color {
+    read_wal_foreground().unwrap_or_else(|e| {
+        eprintln!("hyprsnow: {}, using white", e);
+        Color::WHITE
+    })
+}
+
+fn read_wal_foreground() -> Result<Color, String> {
+    let path = wal_colors_path();
+    let text = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    // One value isn't worth a JSON parser: take the string following the "foreground" key
+    let value = text
+        .split_once("\"foreground\"")
+        .and_then(|(_, rest)| rest.split('"').nth(1))
+        .ok_or_else(|| format!("No foreground color in {}", path.display()))?;
+    parse_color(value)
+}
+
+fn compile_patterns(patterns: &[String]) -> Vec<Regex> {
+    patterns
+        .iter()
+        .filter_map(|p| match Regex::new(p) {
+            Ok(re) => Some(re),
+            Err(e) => {
+                eprintln!("hyprsnow: Ignoring invalid exclude pattern {:?}: {}", p, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Where the config file lives, whether or not it exists yet
+fn config_file_path() -> PathBuf {
     let config_home = std::env::var("XDG_CONFIG_HOME")
         .map(PathBuf::from)
         .unwrap_or_else(|_| {
@@ -45,7 +897,11 @@ pub fn get_config_path() -> Option<PathBuf> {
             PathBuf::from(home).join(".config")
         });
 
-    let config_file = config_home.join("hypr").join("hyprsnow.conf");
+    config_home.join("hypr").join("hyprsnow.conf")
+}
+
+pub fn get_config_path() -> Option<PathBuf> {
+    let config_file = config_file_path();
     if config_file.exists() {
         Some(config_file)
     } else {
@@ -53,63 +909,576 @@ pub fn get_config_path() -> Option<PathBuf> {
     }
 }
 
-pub fn load_config() -> SnowConfig {
-    let path = match get_config_path() {
+/// Options without a default, which `init` writes commented out with an example value. Those
+/// lacking a command-line option bring their own description.
+const EXAMPLE_OPTIONS: [(&str, &str, Option<&str>); 10] = [
+    (
+        "preset",
+        "gentle",
+        Some("Named look: gentle, flurry, blizzard or rain"),
+    ),
+    ("count", "300", None),
+    ("density_per_megapixel", "100", None),
+    ("seed", "42", None),
+    ("monitors", "DP-2", None),
+    ("schedule", "18:00-23:00:8, 23:00-06:00:3", None),
+    ("palette", "\"#a0c4ff\"", None),
+    (
+        "image_path",
+        "/path/to/snowflake.png",
+        Some("Transparent image drawn instead of round snowflakes; repeat the line for several"),
+    ),
+    ("exclude", "^mpv$", None),
+    ("class_colors", "kitty, 0x7fd962", None),
+];
+
+/// A `hyprsnow.conf` setting every `general` option to its default, each described by the help
+/// of the matching command-line option.
+fn default_config_text() -> String {
+    let command = Args::command();
+    let help = |key: &str| {
+        command
+            .get_arguments()
+            .find(|arg| arg.get_id() == key)
+            .and_then(|arg| arg.get_help())
+            .map(|help| help.to_string())
+    };
+    let mut text = String::from(
+        "# hyprsnow config, reloaded whenever it's saved. Every option starts at its default;\n\
+         # the README covers per-monitor blocks and splitting the config into several files.\n\n\
+         general {\n",
+    );
+    let mut option = |key: &str, value: &str, description: Option<String>, commented: bool| {
+        for line in description.iter().flat_map(|d| d.lines()) {
+            text.push_str(&format!("    # {}\n", line));
+        }
+        let prefix = if commented { "# " } else { "" };
+        text.push_str(&format!("    {}{} = {}\n\n", prefix, key, value));
+    };
+
+    for line in SnowConfig::default().to_string().lines() {
+        let Some((key, value)) = line
+            .strip_prefix("general:")
+            .and_then(|l| l.split_once(" = "))
+        else {
+            continue;
+        };
+        // `#` starts a comment, so colors need quotes
+        let value = if value.starts_with('#') {
+            format!("\"{}\"", value)
+        } else {
+            value.to_string()
+        };
+        option(key, &value, help(key), false);
+    }
+    for (key, value, description) in EXAMPLE_OPTIONS {
+        let description = description.map(String::from).or_else(|| help(key));
+        option(key, value, description, true);
+    }
+
+    text.truncate(text.trim_end().len());
+    text.push_str("\n}\n");
+    text
+}
+
+/// Writes a commented config with every default to the config path, unless a config is
+/// already there. Returns where it went.
+pub fn write_default_config() -> Result<PathBuf, String> {
+    let path = config_file_path();
+    if path.exists() {
+        return Err(format!(
+            "{} already exists, leaving it alone",
+            path.display()
+        ));
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("failed to create {}: {}", dir.display(), e))?;
+    }
+    fs::write(&path, default_config_text())
+        .map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// Deepest chain of `source`/`include` lines followed before giving up
+const MAX_SOURCE_DEPTH: usize = 16;
+
+/// Reads a config file with every top-level `source = file` or `include = file` line replaced
+/// by that file's contents, so settings in a later file override earlier ones. `stack` holds
+/// the files being read, to catch files that end up sourcing themselves.
+fn read_with_sources(path: &Path, stack: &mut Vec<PathBuf>) -> Result<String, String> {
+    let path = path
+        .canonicalize()
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    if stack.contains(&path) {
+        return Err(format!("{} is sourced from itself", path.display()));
+    }
+    if stack.len() >= MAX_SOURCE_DEPTH {
+        return Err(format!(
+            "Too many nested sources reading {}",
+            path.display()
+        ));
+    }
+
+    let text = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    stack.push(path.clone());
+    let mut merged = String::with_capacity(text.len());
+    // Lines inside a `{}` block are left to the block, even when they look like a source line
+    let mut block_depth = 0usize;
+    for line in text.lines() {
+        match source_target(line).filter(|_| block_depth == 0) {
+            Some(target) => {
+                let target = expand_home(target);
+                let target = match path.parent() {
+                    Some(dir) if target.is_relative() => dir.join(target),
+                    _ => target,
+                };
+                merged.push_str(&read_with_sources(&target, stack)?);
+            }
+            None => merged.push_str(line),
+        }
+        merged.push('\n');
+        let code = strip_comment(line);
+        let (opened, closed) = (code.matches('{').count(), code.matches('}').count());
+        block_depth = (block_depth + opened).saturating_sub(closed);
+    }
+    stack.pop();
+
+    Ok(merged)
+}
+
+/// `line` without its trailing comment. Only a `#` opening the line or following whitespace,
+/// outside quotes, starts one, so a path like `snow#2.conf` stays whole.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    let mut after_space = true;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted && after_space => return &line[..i],
+            _ => {}
+        }
+        after_space = c.is_whitespace();
+    }
+    line
+}
+
+/// The file named by a `source = ...` or `include = ...` line
+fn source_target(line: &str) -> Option<&str> {
+    let (key, value) = strip_comment(line).split_once('=')?;
+    matches!(key.trim(), "source" | "include").then(|| value.trim().trim_matches('"'))
+}
+
+/// Expands a leading `~` to the home directory
+fn expand_home(path: &str) -> PathBuf {
+    let home = || PathBuf::from(std::env::var("HOME").unwrap_or_default());
+    match path.strip_prefix('~') {
+        Some("") => home(),
+        Some(rest) if rest.starts_with('/') => home().join(&rest[1..]),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Loads the config file on top of the defaults of its `mode`, or of `mode_override` when set.
+/// A file that fails to parse is reported and replaced by the defaults.
+pub fn load_config(mode_override: Option<ParticleMode>) -> SnowConfig {
+    try_load_config(mode_override).unwrap_or_else(|e| {
+        eprintln!("hyprsnow: {}, using defaults", e);
+        SnowConfig::for_mode(mode_override.unwrap_or_default())
+    })
+}
+
+/// Like `load_config`, but hands parse errors back instead of falling back to the defaults.
+/// A missing config file is not an error.
+fn try_load_config(mode_override: Option<ParticleMode>) -> Result<SnowConfig, String> {
+    load_config_file(get_config_path(), mode_override)
+}
+
+/// Parses the config file at `path` on top of the mode's defaults; `None` stands for no file.
+fn load_config_file(
+    path: Option<PathBuf>,
+    mode_override: Option<ParticleMode>,
+) -> Result<SnowConfig, String> {
+    let path = match path {
         Some(p) => p,
-        None => return SnowConfig::default(),
+        None => return Ok(SnowConfig::for_mode(mode_override.unwrap_or_default())),
     };
 
     let mut config = hyprlang::Config::new();
 
-    config.register_category_handler_fn("general", "image_path", |ctx| {
-        println!("Got image path: {}", ctx.value);
-        Ok(())
-    });
+    config.register_category_handler_fn("general", "image_path", |_| Ok(()));
+    config.register_category_handler_fn("general", "exclude", |_| Ok(()));
+    config.register_category_handler_fn("general", "palette", |_| Ok(()));
+    config.register_category_handler_fn("general", "class_colors", |_| Ok(()));
+    config.register_category_handler_fn("general", "monitors", |_| Ok(()));
+    config.register_special_category(SpecialCategoryDescriptor::keyed("monitor", "name"));
 
-    if config.parse_file(&path).is_err() {
-        return SnowConfig::default();
+    let text = read_with_sources(&path, &mut Vec::new())?;
+    if let Err(e) = config.parse(&text) {
+        return Err(format!("Failed to parse {}: {}", path.display(), e));
     }
 
-    SnowConfig {
+    let explicit_mode = mode_override.or_else(|| {
+        config.get_string("general:mode").ok().and_then(|v| {
+            ParticleMode::from_str(v, true)
+                .inspect_err(|e| eprintln!("hyprsnow: Invalid mode: {}", e))
+                .ok()
+        })
+    });
+    let mut defaults = SnowConfig::for_mode(explicit_mode.unwrap_or_default());
+    if let Ok(name) = config.get_string("general:preset")
+        && let Err(e) = apply_preset(&mut defaults, name)
+    {
+        eprintln!("hyprsnow: Ignoring preset: {}", e);
+    }
+    let mode = explicit_mode.unwrap_or(defaults.mode);
+    let color = match config.get_string("general:color") {
+        Ok(value) if value.trim_matches('"') == "auto" => Some(theme_color()),
+        _ => get_color(&config, "general:color"),
+    };
+
+    let mut loaded = SnowConfig {
+        mode,
         intensity: config
             .get_int("general:intensity")
             .map(|v| v.clamp(1, 10) as u8)
-            .unwrap_or(3),
+            .unwrap_or(defaults.intensity),
+        count: config
+            .get_int("general:count")
+            .ok()
+            .map(|v| v.max(0) as usize),
+        density_per_megapixel: config
+            .get_float("general:density_per_megapixel")
+            .ok()
+            .map(|v| (v as f32).max(0.0)),
         size_min: config
             .get_float("general:size_min")
             .map(|v| v as f32)
-            .unwrap_or(2.0),
+            .unwrap_or(defaults.size_min),
         size_max: config
             .get_float("general:size_max")
             .map(|v| v as f32)
-            .unwrap_or(5.0),
+            .unwrap_or(defaults.size_max),
         speed_min: config
             .get_float("general:speed_min")
             .map(|v| v as f32)
-            .unwrap_or(30.0),
+            .unwrap_or(defaults.speed_min),
         speed_max: config
             .get_float("general:speed_max")
             .map(|v| v as f32)
-            .unwrap_or(80.0),
+            .unwrap_or(defaults.speed_max),
         drift: config
             .get_float("general:drift")
             .map(|v| v as f32)
-            .unwrap_or(20.0),
+            .unwrap_or(defaults.drift),
         max_opacity: config
             .get_float("general:max_opacity")
             .map(|v| (v as f32).clamp(0.0, 1.0))
-            .unwrap_or(1.0),
+            .unwrap_or(defaults.max_opacity),
+        // `wind` predates the vertical component and stays as the name for `wind_x`
+        wind_x: config
+            .get_float("general:wind_x")
+            .or_else(|_| config.get_float("general:wind"))
+            .map(|v| v as f32)
+            .unwrap_or(defaults.wind_x),
+        wind_y: config
+            .get_float("general:wind_y")
+            .map(|v| v as f32)
+            .unwrap_or(defaults.wind_y),
+        gust_strength: config
+            .get_float("general:gust_strength")
+            .map(|v| (v as f32).max(0.0))
+            .unwrap_or(defaults.gust_strength),
+        max_pile_height: config
+            .get_float("general:max_pile_height")
+            .map(|v| (v as f32).max(0.0))
+            .unwrap_or(defaults.max_pile_height),
+        layer_mode: config
+            .get_string("general:layer_mode")
+            .ok()
+            .and_then(|v| {
+                LayerMode::from_str(v, true)
+                    .inspect_err(|e| eprintln!("hyprsnow: Invalid layer_mode: {}", e))
+                    .ok()
+            })
+            .unwrap_or(defaults.layer_mode),
+        melt_duration: config
+            .get_float("general:melt_duration")
+            .map(|v| (v as f32).max(0.1))
+            .unwrap_or(defaults.melt_duration),
+        melt_curve: config
+            .get_string("general:melt_curve")
+            .ok()
+            .and_then(|v| {
+                MeltCurve::from_str(v, true)
+                    .inspect_err(|e| eprintln!("hyprsnow: Invalid melt_curve: {}", e))
+                    .ok()
+            })
+            .unwrap_or(defaults.melt_curve),
+        fps: config
+            .get_int("general:fps")
+            .map(|v| v.clamp(10, 144) as u32)
+            .unwrap_or(defaults.fps),
+        spin_min: config
+            .get_float("general:spin_min")
+            .map(|v| v as f32)
+            .unwrap_or(defaults.spin_min),
+        spin_max: config
+            .get_float("general:spin_max")
+            .map(|v| v as f32)
+            .unwrap_or(defaults.spin_max),
+        idle_when_empty: config
+            .get_int("general:idle_when_empty")
+            .map(|v| v != 0)
+            .unwrap_or(defaults.idle_when_empty),
+        color: color.unwrap_or(defaults.color),
+        palette: config
+            .get_handler_calls("general:palette")
+            .map(|colors| {
+                colors
+                    .iter()
+                    .filter_map(|c| {
+                        parse_color(c)
+                            .inspect_err(|e| eprintln!("hyprsnow: Invalid palette entry: {}", e))
+                            .ok()
+                    })
+                    .collect()
+            })
+            // An explicit color replaces the mode's palette rather than being hidden by it
+            .unwrap_or(if color.is_some() {
+                Vec::new()
+            } else {
+                defaults.palette
+            }),
+        gravity: config
+            .get_float("general:gravity")
+            .map(|v| (v as f32).max(0.0))
+            .unwrap_or(defaults.gravity),
+        focus_melt_multiplier: config
+            .get_float("general:focus_melt_multiplier")
+            .map(|v| (v as f32).max(0.0))
+            .unwrap_or(defaults.focus_melt_multiplier),
+        fade_in_distance: config
+            .get_float("general:fade_in_distance")
+            .map(|v| (v as f32).max(0.0))
+            .unwrap_or(defaults.fade_in_distance),
+        parallax: config
+            .get_int("general:parallax")
+            .map(|v| v != 0)
+            .unwrap_or(defaults.parallax),
+        land: config
+            .get_int("general:land")
+            .map(|v| v != 0)
+            .unwrap_or(defaults.land),
+        soft_edges: config
+            .get_int("general:soft_edges")
+            .map(|v| v != 0)
+            .unwrap_or(defaults.soft_edges),
+        startup_ramp: config
+            .get_int("general:startup_ramp")
+            .map(|v| v != 0)
+            .unwrap_or(defaults.startup_ramp),
+        image_size_map: config
+            .get_int("general:image_size_map")
+            .map(|v| v != 0)
+            .unwrap_or(defaults.image_size_map),
+        hide_on_fullscreen: config
+            .get_int("general:hide_on_fullscreen")
+            .map(|v| v != 0)
+            .unwrap_or(defaults.hide_on_fullscreen),
+        cursor_interaction: config
+            .get_int("general:cursor_interaction")
+            .map(|v| v != 0)
+            .unwrap_or(defaults.cursor_interaction),
+        drift_freq: config
+            .get_float("general:drift_freq")
+            .map(|v| (v as f32).max(0.0))
+            .unwrap_or(defaults.drift_freq),
+        shell_layer: config
+            .get_string("general:shell_layer")
+            .ok()
+            .and_then(|v| {
+                ShellLayer::from_str(v, true)
+                    .inspect_err(|e| eprintln!("hyprsnow: Invalid shell_layer: {}", e))
+                    .ok()
+            })
+            .unwrap_or(defaults.shell_layer),
+        seed: config.get_int("general:seed").ok().map(|v| v as u64),
+        land_margin: config
+            .get_float("general:land_margin")
+            .map(|v| (v as f32).max(0.0))
+            .unwrap_or(defaults.land_margin),
+        max_lifetime: config
+            .get_float("general:max_lifetime")
+            .map(|v| (v as f32).max(0.0))
+            .unwrap_or(defaults.max_lifetime),
+        clump_chance: config
+            .get_float("general:clump_chance")
+            .map(|v| (v as f32).clamp(0.0, 1.0))
+            .unwrap_or(defaults.clump_chance),
+        land_on_bars: config
+            .get_int("general:land_on_bars")
+            .map(|v| v != 0)
+            .unwrap_or(defaults.land_on_bars),
+        land_on: config
+            .get_string("general:land_on")
+            .ok()
+            .and_then(|v| {
+                LandOn::from_str(v, true)
+                    .inspect_err(|e| eprintln!("hyprsnow: Invalid land_on: {}", e))
+                    .ok()
+            })
+            .unwrap_or(defaults.land_on),
+        orphan_behavior: config
+            .get_string("general:orphan_behavior")
+            .ok()
+            .and_then(|v| {
+                OrphanBehavior::from_str(v, true)
+                    .inspect_err(|e| eprintln!("hyprsnow: Invalid orphan_behavior: {}", e))
+                    .ok()
+            })
+            .unwrap_or(defaults.orphan_behavior),
+        tint_strength: config
+            .get_float("general:tint_strength")
+            .map(|v| (v as f32).clamp(0.0, 1.0))
+            .unwrap_or(defaults.tint_strength),
+        tint_color: get_color(&config, "general:tint_color").unwrap_or(defaults.tint_color),
+        twinkle: config
+            .get_int("general:twinkle")
+            .map(|v| v != 0)
+            .unwrap_or(defaults.twinkle),
+        suppress_on: config
+            .get_string("general:suppress_on")
+            .ok()
+            .and_then(|v| {
+                parse_suppress_on(v)
+                    .inspect_err(|e| eprintln!("hyprsnow: Invalid suppress_on: {}", e))
+                    .ok()
+            })
+            .unwrap_or(defaults.suppress_on),
+        shapes: config
+            .get_string("general:shape")
+            .ok()
+            .and_then(|v| {
+                parse_shapes(v)
+                    .inspect_err(|e| eprintln!("hyprsnow: Invalid shape: {}", e))
+                    .ok()
+            })
+            .unwrap_or(defaults.shapes),
+        debug: config
+            .get_int("general:debug")
+            .map(|v| v != 0)
+            .unwrap_or(defaults.debug),
+        avoid_cursor_radius: config
+            .get_float("general:avoid_cursor_radius")
+            .map(|v| (v as f32).max(0.0))
+            .unwrap_or(defaults.avoid_cursor_radius),
+        melt_size_variation: config
+            .get_float("general:melt_size_variation")
+            .map(|v| (v as f32).clamp(0.0, 1.0))
+            .unwrap_or(defaults.melt_size_variation),
+        active_monitor_only: config
+            .get_int("general:active_monitor_only")
+            .map(|v| v != 0)
+            .unwrap_or(defaults.active_monitor_only),
+        stick_chance: config
+            .get_float("general:stick_chance")
+            .map(|v| (v as f32).clamp(0.0, 1.0))
+            .unwrap_or(defaults.stick_chance),
+        spawn_bias: config
+            .get_float("general:spawn_bias")
+            .map(|v| (v as f32).clamp(-1.0, 1.0))
+            .unwrap_or(defaults.spawn_bias),
+        reset_opacity_threshold: config
+            .get_float("general:reset_opacity_threshold")
+            .map(|v| (v as f32).clamp(0.0, 1.0))
+            .unwrap_or(defaults.reset_opacity_threshold),
+        behind_slowdown: config
+            .get_float("general:behind_slowdown")
+            .map(|v| (v as f32).clamp(0.0, 1.0))
+            .unwrap_or(defaults.behind_slowdown),
+        trail_strength: config
+            .get_float("general:trail_strength")
+            .map(|v| (v as f32).clamp(0.0, 1.0))
+            .unwrap_or(defaults.trail_strength),
+        per_monitor_wrap: config
+            .get_int("general:per_monitor_wrap")
+            .map(|v| v != 0)
+            .unwrap_or(defaults.per_monitor_wrap),
+        region: config
+            .get_int("general:region")
+            .map(|v| v.max(0) as u32)
+            .unwrap_or(defaults.region),
+        anchor: config
+            .get_string("general:anchor")
+            .ok()
+            .and_then(|v| {
+                RegionAnchor::from_str(v, true)
+                    .inspect_err(|e| eprintln!("hyprsnow: Invalid anchor: {}", e))
+                    .ok()
+            })
+            .unwrap_or(defaults.anchor),
         image_paths: config
             .get_handler_calls("general:image_path")
-            .filter(|v| !v.is_empty())
-            .cloned()
-    }
+            .map(|paths| paths.iter().map(PathBuf::from).collect())
+            .unwrap_or_default(),
+        schedule: config
+            .get_string("general:schedule")
+            .ok()
+            .and_then(|v| {
+                parse_schedule(v)
+                    .inspect_err(|e| eprintln!("hyprsnow: Invalid schedule: {}", e))
+                    .ok()
+            })
+            .unwrap_or_default(),
+        exclude: config
+            .get_handler_calls("general:exclude")
+            .map(|patterns| compile_patterns(patterns))
+            .unwrap_or_default(),
+        class_colors: config
+            .get_handler_calls("general:class_colors")
+            .map(|entries| {
+                entries
+                    .iter()
+                    .filter_map(|entry| {
+                        parse_class_color(entry)
+                            .inspect_err(|e| eprintln!("hyprsnow: Invalid class_colors: {}", e))
+                            .ok()
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+        monitors: config
+            .get_handler_calls("general:monitors")
+            .map(|names| names.iter().map(|n| n.trim().to_string()).collect())
+            .unwrap_or_default(),
+        monitor_intensity: config
+            .list_special_category_keys("monitor")
+            .into_iter()
+            .filter_map(|name| {
+                let values = config.get_special_category("monitor", &name).ok()?;
+                let intensity = values.get("intensity")?.as_int().ok()?;
+                Some((name, intensity.clamp(1, 10) as u8))
+            })
+            .collect(),
+    };
+    loaded.fix_ranges();
+
+    Ok(loaded)
 }
 
 pub fn apply_cli_overrides(config: &mut SnowConfig, args: &Args) {
     if let Some(v) = args.intensity {
         config.intensity = v;
     }
+    if let Some(v) = args.count {
+        config.count = Some(v);
+    }
+    if let Some(v) = args.density_per_megapixel {
+        config.density_per_megapixel = Some(v.max(0.0));
+    }
     if let Some(v) = args.size_min {
         config.size_min = v;
     }
@@ -128,12 +1497,183 @@ pub fn apply_cli_overrides(config: &mut SnowConfig, args: &Args) {
     if let Some(v) = args.max_opacity {
         config.max_opacity = v.clamp(0.0, 1.0);
     }
+    if let Some(v) = args.wind_x {
+        config.wind_x = v;
+    }
+    if let Some(v) = args.wind_y {
+        config.wind_y = v;
+    }
+    if let Some(v) = args.gust_strength {
+        config.gust_strength = v.max(0.0);
+    }
+    if let Some(v) = args.max_pile_height {
+        config.max_pile_height = v.max(0.0);
+    }
+    if let Some(v) = args.layer_mode {
+        config.layer_mode = v;
+    }
+    if let Some(v) = args.melt_duration {
+        config.melt_duration = v.max(0.1);
+    }
+    if let Some(v) = args.melt_curve {
+        config.melt_curve = v;
+    }
+    if let Some(v) = args.fps {
+        config.fps = v;
+    }
+    if let Some(v) = args.spin_min {
+        config.spin_min = v;
+    }
+    if let Some(v) = args.spin_max {
+        config.spin_max = v;
+    }
+    if let Some(v) = args.idle_when_empty {
+        config.idle_when_empty = v;
+    }
+    if let Some(v) = args.color {
+        config.color = v;
+        if args.palette.is_none() && config.palette == SnowConfig::for_mode(config.mode).palette {
+            config.palette.clear();
+        }
+    }
+    if let Some(v) = &args.palette {
+        config.palette = v.clone();
+    }
+    if let Some(v) = args.gravity {
+        config.gravity = v.max(0.0);
+    }
+    if let Some(v) = args.focus_melt_multiplier {
+        config.focus_melt_multiplier = v.max(0.0);
+    }
+    if let Some(v) = args.fade_in_distance {
+        config.fade_in_distance = v.max(0.0);
+    }
+    if let Some(v) = args.parallax {
+        config.parallax = v;
+    }
+    if let Some(v) = args.land {
+        config.land = v;
+    }
+    if let Some(v) = args.soft_edges {
+        config.soft_edges = v;
+    }
+    if let Some(v) = args.startup_ramp {
+        config.startup_ramp = v;
+    }
+    if let Some(v) = args.image_size_map {
+        config.image_size_map = v;
+    }
+    if let Some(v) = args.hide_on_fullscreen {
+        config.hide_on_fullscreen = v;
+    }
+    if let Some(v) = args.cursor_interaction {
+        config.cursor_interaction = v;
+    }
+    if let Some(v) = args.drift_freq {
+        config.drift_freq = v.max(0.0);
+    }
+    if let Some(v) = args.shell_layer {
+        config.shell_layer = v;
+    }
+    if let Some(v) = args.seed {
+        config.seed = Some(v);
+    }
+    if let Some(v) = args.land_margin {
+        config.land_margin = v.max(0.0);
+    }
+    if let Some(v) = args.max_lifetime {
+        config.max_lifetime = v.max(0.0);
+    }
+    if let Some(v) = args.clump_chance {
+        config.clump_chance = v.clamp(0.0, 1.0);
+    }
+    if let Some(v) = args.land_on_bars {
+        config.land_on_bars = v;
+    }
+    if let Some(v) = args.land_on {
+        config.land_on = v;
+    }
+    if let Some(v) = args.orphan_behavior {
+        config.orphan_behavior = v;
+    }
+    if let Some(v) = args.tint_strength {
+        config.tint_strength = v.clamp(0.0, 1.0);
+    }
+    if let Some(v) = args.tint_color {
+        config.tint_color = v;
+    }
+    if let Some(v) = args.twinkle {
+        config.twinkle = v;
+    }
+    if let Some(v) = args.debug {
+        config.debug = v;
+    }
+    if let Some(v) = args.avoid_cursor_radius {
+        config.avoid_cursor_radius = v.max(0.0);
+    }
+    if let Some(v) = args.melt_size_variation {
+        config.melt_size_variation = v.clamp(0.0, 1.0);
+    }
+    if let Some(v) = args.active_monitor_only {
+        config.active_monitor_only = v;
+    }
+    if let Some(v) = args.stick_chance {
+        config.stick_chance = v.clamp(0.0, 1.0);
+    }
+    if let Some(v) = args.spawn_bias {
+        config.spawn_bias = v.clamp(-1.0, 1.0);
+    }
+    if let Some(v) = args.reset_opacity_threshold {
+        config.reset_opacity_threshold = v.clamp(0.0, 1.0);
+    }
+    if let Some(v) = args.behind_slowdown {
+        config.behind_slowdown = v.clamp(0.0, 1.0);
+    }
+    if let Some(v) = args.trail_strength {
+        config.trail_strength = v.clamp(0.0, 1.0);
+    }
+    if let Some(v) = args.per_monitor_wrap {
+        config.per_monitor_wrap = v;
+    }
+    if let Some(v) = args.region {
+        config.region = v;
+    }
+    if let Some(v) = args.anchor {
+        config.anchor = v;
+    }
     if let Some(v) = &args.image_path {
-        config.image_paths = Some(v.clone());
+        config.image_paths = v.clone();
+    }
+    if let Some(v) = &args.suppress_on {
+        config.suppress_on = v.clone();
     }
+    if let Some(v) = &args.shape {
+        config.shapes = v.clone();
+    }
+    if let Some(v) = &args.schedule {
+        config.schedule = v.clone();
+    }
+    if let Some(v) = &args.exclude {
+        config.exclude = compile_patterns(v);
+    }
+    if let Some(v) = &args.class_colors {
+        config.class_colors = v.clone();
+    }
+    if let Some(v) = &args.monitors {
+        config.monitors = v.clone();
+    }
+    config.fix_ranges();
 }
 
-pub fn spawn_config_watcher() -> mpsc::Receiver<ConfigEvent> {
+/// Reads the config file at `path` again for a hot reload, with the command-line options on
+/// top so a saved file never undoes them.
+fn reload_config(path: Option<PathBuf>, args: &Args) -> Result<SnowConfig, String> {
+    let mut config = load_config_file(path, args.mode)?;
+    apply_cli_overrides(&mut config, args);
+    Ok(config)
+}
+
+pub fn spawn_config_watcher(args: Args) -> mpsc::Receiver<ConfigEvent> {
     let (tx, rx) = mpsc::channel();
 
     thread::spawn(move || {
@@ -156,33 +1696,25 @@ pub fn spawn_config_watcher() -> mpsc::Receiver<ConfigEvent> {
             .unwrap_or("hyprsnow.conf")
             .to_string();
 
-        let tx_clone = tx.clone();
-        let last_reload = std::sync::Arc::new(std::sync::Mutex::new(Instant::now()));
-        let last_reload_clone = last_reload.clone();
+        // Editors often truncate and rewrite in separate steps, so reload only once the
+        // file has been quiet for the debounce window rather than on the first event.
+        let (change_tx, change_rx) = mpsc::channel();
         let debounce_duration = Duration::from_millis(100);
+        let wal_path = wal_colors_path();
+        let wal_changes = wal_path.clone();
 
         let mut watcher = match notify::recommended_watcher(move |res: Result<Event, _>| {
-            if let Ok(event) = res {
-                match event.kind {
-                    EventKind::Modify(_) | EventKind::Create(_) => {
-                        // Check if this event is for our config file
-                        let is_config_file = event
-                            .paths
-                            .iter()
-                            .any(|p| p.file_name().and_then(|n| n.to_str()) == Some(&config_filename));
-
-                        if is_config_file {
-                            // Debounce: skip if we reloaded recently
-                            let mut last = last_reload_clone.lock().unwrap();
-                            if last.elapsed() > debounce_duration {
-                                *last = Instant::now();
-                                drop(last);
-                                let new_config = load_config();
-                                let _ = tx_clone.send(ConfigEvent::ConfigChanged(new_config));
-                            }
-                        }
-                    }
-                    _ => {}
+            if let Ok(event) = res
+                && matches!(event.kind, EventKind::Modify(_) | EventKind::Create(_))
+            {
+                // Check if this event is for our config file or the pywal theme
+                let is_config_file = event.paths.iter().any(|p| {
+                    p.file_name().and_then(|n| n.to_str()) == Some(&config_filename)
+                        || *p == wal_changes
+                });
+
+                if is_config_file {
+                    let _ = change_tx.send(());
                 }
             }
         }) {
@@ -198,11 +1730,123 @@ pub fn spawn_config_watcher() -> mpsc::Receiver<ConfigEvent> {
             return;
         }
 
-        // Keep thread alive - watcher is dropped when thread ends
-        loop {
-            thread::park();
+        // `color = auto` follows pywal, so a new theme reloads the config too
+        if let Some(wal_dir) = wal_path.parent()
+            && wal_dir.is_dir()
+            && let Err(e) = watcher.watch(wal_dir, RecursiveMode::NonRecursive)
+        {
+            eprintln!("hyprsnow: Failed to watch the pywal cache: {}", e);
+        }
+
+        // The watcher lives as long as this loop, which ends once the app drops the receiver
+        while change_rx.recv().is_ok() {
+            while change_rx.recv_timeout(debounce_duration).is_ok() {}
+
+            let event = match reload_config(get_config_path(), &args) {
+                Ok(config) => ConfigEvent::ConfigChanged(Box::new(config)),
+                Err(e) => ConfigEvent::ParseError(e),
+            };
+            if tx.send(event).is_err() {
+                break;
+            }
         }
+
+        drop(watcher);
     });
 
     rx
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn reloads_keep_command_line_options() {
+        let name = format!("hyprsnow-test-{}.conf", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        let text = "general {\n    mode = leaves\n    intensity = 3\n}\n";
+        fs::write(&path, text).unwrap();
+        let args = Args::parse_from(["hyprsnow", "--mode", "rain", "--intensity", "9"]);
+        let reloaded = reload_config(Some(path.clone()), &args);
+        let _ = fs::remove_file(&path);
+
+        let config = reloaded.unwrap();
+        assert_eq!(config.mode, ParticleMode::Rain);
+        assert_eq!(config.intensity, 9);
+    }
+
+    #[test]
+    fn source_lines_keep_hashes_inside_paths() {
+        let target = source_target("source = ~/snow#2.conf");
+        assert_eq!(target, Some("~/snow#2.conf"));
+        let target = source_target("include = \"a #b.conf\" # winter");
+        assert_eq!(target, Some("a #b.conf"));
+        assert_eq!(source_target("# source = old.conf"), None);
+    }
+
+    #[test]
+    fn only_top_level_source_lines_are_expanded() {
+        let dir = std::env::temp_dir().join(format!("hyprsnow-sources-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("snow#2.conf"), "general:intensity = 4").unwrap();
+        let text = "source = snow#2.conf\nmonitor[DP-1] {\n    source = missing.conf\n}\n";
+        fs::write(dir.join("main.conf"), text).unwrap();
+        let merged = read_with_sources(&dir.join("main.conf"), &mut Vec::new());
+        let _ = fs::remove_dir_all(&dir);
+
+        let merged = merged.unwrap();
+        assert!(merged.starts_with("general:intensity = 4\n"));
+        assert!(merged.contains("    source = missing.conf\n"));
+    }
+
+    #[test]
+    fn invalid_choices_fall_back_to_the_default() {
+        let name = format!("hyprsnow-choices-{}.conf", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        let text = "general {\n    layer_mode = behnd\n    melt_curve = instant\n}\n";
+        fs::write(&path, text).unwrap();
+        let loaded = load_config_file(Some(path.clone()), None);
+        let _ = fs::remove_file(&path);
+
+        let config = loaded.unwrap();
+        assert_eq!(config.layer_mode, SnowConfig::default().layer_mode);
+        assert_eq!(config.melt_curve, MeltCurve::Instant);
+    }
+
+    #[test]
+    fn fix_ranges_swaps_reversed_pairs() {
+        let mut config = SnowConfig {
+            size_min: 5.0,
+            size_max: 2.0,
+            speed_min: 80.0,
+            speed_max: 20.0,
+            ..SnowConfig::default()
+        };
+        config.fix_ranges();
+
+        assert_eq!((config.size_min, config.size_max), (2.0, 5.0));
+        assert_eq!((config.speed_min, config.speed_max), (20.0, 80.0));
+    }
+
+    #[test]
+    fn fix_ranges_raises_sizes_that_are_not_positive() {
+        let mut config = SnowConfig {
+            size_min: -3.0,
+            size_max: 0.0,
+            ..SnowConfig::default()
+        };
+        config.fix_ranges();
+        assert_eq!(config.size_min, MIN_FLAKE_SIZE);
+        assert_eq!(config.size_max, MIN_FLAKE_SIZE);
+
+        let mut config = SnowConfig {
+            size_min: 4.0,
+            size_max: -2.0,
+            ..SnowConfig::default()
+        };
+        config.fix_ranges();
+        assert_eq!((config.size_min, config.size_max), (MIN_FLAKE_SIZE, 4.0));
+    }
+}
@@ -1,33 +1,106 @@
-use hyprland::data::{Clients, Monitors, Workspace, Workspaces};
+use crate::source::{WindowEvent, WindowSource};
+use hyprland::data::{Client, Clients, CursorPosition, FullscreenMode, Layers, Monitors};
 use hyprland::event_listener::AsyncEventListener;
 use hyprland::prelude::*;
 use hyprland::shared::Address;
+use iced::{Point, Rectangle, Size};
+use std::any::Any;
+use std::collections::HashSet;
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::mpsc;
 use std::thread;
+use std::time::{Duration, Instant};
 
+/// Backoff bounds between attempts to reach Hyprland again after losing its IPC socket
+const RECONNECT_MIN: Duration = Duration::from_secs(1);
+const RECONNECT_MAX: Duration = Duration::from_secs(30);
+
+/// A visible window's full box in global layout coordinates, so callers can reason about both
+/// its top edge and how far down it reaches.
 #[derive(Clone)]
 pub struct WindowRect {
     pub address: Address,
+    pub class: String,
+    pub title: String,
     pub x: f32,
     pub y: f32,
     pub width: f32,
+    pub height: f32,
+    /// Floating rather than placed by the tiling layout; bars and docks never float
+    pub floating: bool,
+}
+
+/// Width of the x-columns windows are bucketed into by `WindowIndex`
+const INDEX_COLUMN_WIDTH: f32 = 128.0;
+
+/// Windows bucketed by the x-columns they span, so lookups by x only visit nearby windows
+/// instead of every window on the workspace.
+#[derive(Default)]
+pub struct WindowIndex {
+    windows: Vec<WindowRect>,
+    /// Indices into `windows` per column, in their original order
+    columns: Vec<Vec<usize>>,
+    origin: f32,
+}
+
+impl WindowIndex {
+    pub fn new(windows: Vec<WindowRect>) -> Self {
+        let origin = windows.iter().map(|w| w.x).fold(f32::INFINITY, f32::min);
+        let origin = if origin.is_finite() { origin } else { 0.0 };
+
+        let mut index = Self {
+            windows,
+            columns: Vec::new(),
+            origin,
+        };
+
+        for (i, w) in index.windows.iter().enumerate() {
+            let first = index.column_at(w.x);
+            let last = index.column_at(w.x + w.width.max(0.0));
+            if index.columns.len() <= last {
+                index.columns.resize(last + 1, Vec::new());
+            }
+            for column in &mut index.columns[first..=last] {
+                column.push(i);
+            }
+        }
+
+        index
+    }
+
+    fn column_at(&self, x: f32) -> usize {
+        ((x - self.origin) / INDEX_COLUMN_WIDTH).max(0.0) as usize
+    }
+
+    /// Windows whose horizontal extent contains `x`, in the order they were indexed.
+    pub fn at_x(&self, x: f32) -> impl Iterator<Item = &WindowRect> {
+        let column = if x < self.origin {
+            &[][..]
+        } else {
+            self.columns
+                .get(self.column_at(x))
+                .map_or(&[][..], |c| c.as_slice())
+        };
+
+        column
+            .iter()
+            .map(|&i| &self.windows[i])
+            .filter(move |w| x >= w.x && x <= w.x + w.width)
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct MonitorRect {
+    pub name: String,
     pub x: f32,
     pub y: f32,
     pub width: f32,
     pub height: f32,
     pub has_fullscreen: bool,
+    pub has_maximized: bool,
 }
 
-#[derive(Debug, Clone)]
-pub enum HyprlandEvent {
-    WindowsChanged,
-}
-
-pub fn get_total_screen_bounds() -> (f32, f32, f32, f32) {
+fn get_total_screen_bounds() -> (f32, f32, f32, f32) {
     match Monitors::get() {
         Ok(monitors) => {
             let mut min_x = i32::MAX;
@@ -52,121 +125,345 @@ pub fn get_total_screen_bounds() -> (f32, f32, f32, f32) {
     }
 }
 
-pub fn get_hyprland_windows() -> Vec<WindowRect> {
-    let active_workspace_id = match Workspace::get_active() {
-        Ok(ws) => ws.id,
+/// Windows currently on screen: those on any monitor's active or open special workspace, plus
+/// pinned windows, which show on every workspace.
+fn get_hyprland_windows() -> Vec<WindowRect> {
+    let visible_workspaces: HashSet<_> = match Monitors::get() {
+        Ok(monitors) => monitors
+            .iter()
+            .flat_map(|m| [m.active_workspace.id, m.special_workspace.id])
+            .filter(|&id| id != 0)
+            .collect(),
         Err(_) => return Vec::new(),
     };
 
+    let mut seen = HashSet::new();
     match Clients::get() {
         Ok(clients) => clients
             .iter()
-            .filter(|c| c.workspace.id == active_workspace_id)
+            .filter(|c| c.mapped && (c.pinned || visible_workspaces.contains(&c.workspace.id)))
+            .filter(|c| seen.insert(c.address.clone()))
             .map(|c| WindowRect {
                 address: c.address.clone(),
+                class: c.class.clone(),
+                title: c.title.clone(),
                 x: c.at.0 as f32,
                 y: c.at.1 as f32,
                 width: c.size.0 as f32,
+                height: c.size.1 as f32,
+                floating: c.floating,
             })
             .collect(),
         Err(_) => Vec::new(),
     }
 }
 
-pub fn get_monitors_with_fullscreen_state() -> Vec<MonitorRect> {
+/// Layer-shell levels holding panels and bars: `bottom` (1) and `top` (2). The background and
+/// overlay levels hold wallpapers, notifications and the like.
+const BAR_LEVELS: [&str; 2] = ["1", "2"];
+
+/// Layer-shell surfaces such as bars and docks, which `Clients` doesn't list. The namespace
+/// stands in for the class; our own surface is left out.
+fn get_layer_surfaces() -> Vec<WindowRect> {
+    let Ok(layers) = Layers::get() else {
+        return Vec::new();
+    };
+
+    layers
+        .iter()
+        .flat_map(|(_, display)| {
+            BAR_LEVELS
+                .iter()
+                .filter_map(|level| display.levels.get(*level))
+                .flatten()
+        })
+        .filter(|layer| layer.namespace != "hyprsnow" && layer.w > 0 && layer.h > 0)
+        .map(|layer| WindowRect {
+            address: layer.address.clone(),
+            class: layer.namespace.clone(),
+            title: String::new(),
+            x: layer.x as f32,
+            y: layer.y as f32,
+            width: layer.w as f32,
+            height: layer.h as f32,
+            floating: false,
+        })
+        .collect()
+}
+
+fn get_active_window_address() -> Option<Address> {
+    Client::get_active().ok().flatten().map(|c| c.address)
+}
+
+/// Global pointer position; the overlay ignores input, so it can't track the mouse itself.
+fn get_cursor_position() -> Option<(f32, f32)> {
+    CursorPosition::get().ok().map(|p| (p.x as f32, p.y as f32))
+}
+
+fn get_monitors_with_fullscreen_state() -> Vec<MonitorRect> {
     let monitors = match Monitors::get() {
         Ok(m) => m,
         Err(_) => return Vec::new(),
     };
 
-    let workspaces = Workspaces::get().ok();
+    // A workspace's own fullscreen flag is set for maximized windows as well, so go by the
+    // mode of each window instead
+    let clients = Clients::get().ok();
 
     monitors
         .iter()
         .map(|monitor| {
-            let has_fullscreen = workspaces
-                .as_ref()
-                .and_then(|ws| {
-                    ws.iter()
-                        .find(|w| w.id == monitor.active_workspace.id)
-                        .map(|w| w.fullscreen)
-                })
-                .unwrap_or(false);
+            // A special workspace shown over the monitor covers the regular one, so a
+            // fullscreened scratchpad counts too. Its id is 0 when none is open.
+            let shown = |c: &&Client| {
+                c.mapped
+                    && (c.workspace.id == monitor.active_workspace.id
+                        || (monitor.special_workspace.id != 0
+                            && c.workspace.id == monitor.special_workspace.id))
+            };
+            let modes: Vec<FullscreenMode> = clients
+                .iter()
+                .flat_map(|clients| clients.iter())
+                .filter(shown)
+                .map(|c| c.fullscreen)
+                .collect();
+            let has_fullscreen = modes.iter().any(|mode| {
+                matches!(
+                    mode,
+                    FullscreenMode::Fullscreen | FullscreenMode::MaximizedFullscreen
+                )
+            });
+            let has_maximized = modes.contains(&FullscreenMode::Maximized);
 
             MonitorRect {
+                name: monitor.name.clone(),
                 x: monitor.x as f32,
                 y: monitor.y as f32,
                 width: monitor.width as f32,
                 height: monitor.height as f32,
                 has_fullscreen,
+                has_maximized,
             }
         })
         .collect()
 }
 
-pub fn spawn_event_listener() -> mpsc::Receiver<HyprlandEvent> {
+/// Registers the handlers forwarding Hyprland events into `tx` on a fresh listener.
+fn build_listener(tx: &mpsc::Sender<WindowEvent>) -> AsyncEventListener {
+    let mut event_listener = AsyncEventListener::new();
+
+    let tx_clone = tx.clone();
+    event_listener.add_window_opened_handler(move |_| {
+        let tx = tx_clone.clone();
+        Box::pin(async move {
+            let _ = tx.send(WindowEvent::WindowOpened);
+        })
+    });
+
+    let tx_clone = tx.clone();
+    event_listener.add_window_closed_handler(move |address| {
+        let tx = tx_clone.clone();
+        Box::pin(async move {
+            let _ = tx.send(WindowEvent::WindowClosed(address));
+        })
+    });
+
+    let tx_clone = tx.clone();
+    event_listener.add_window_moved_handler(move |_| {
+        let tx = tx_clone.clone();
+        Box::pin(async move {
+            let _ = tx.send(WindowEvent::WindowMoved);
+        })
+    });
+
+    let tx_clone = tx.clone();
+    event_listener.add_active_window_changed_handler(move |data| {
+        let tx = tx_clone.clone();
+        Box::pin(async move {
+            let _ = tx.send(WindowEvent::ActiveWindowChanged(data.map(|d| d.address)));
+        })
+    });
+
+    let tx_clone = tx.clone();
+    event_listener.add_workspace_changed_handler(move |_| {
+        let tx = tx_clone.clone();
+        Box::pin(async move {
+            let _ = tx.send(WindowEvent::WorkspaceChanged);
+        })
+    });
+
+    let tx_clone = tx.clone();
+    event_listener.add_fullscreen_state_changed_handler(move |_| {
+        let tx = tx_clone.clone();
+        Box::pin(async move {
+            let _ = tx.send(WindowEvent::FullscreenChanged);
+        })
+    });
+
+    let tx_clone = tx.clone();
+    event_listener.add_changed_special_handler(move |_| {
+        let tx = tx_clone.clone();
+        Box::pin(async move {
+            let _ = tx.send(WindowEvent::SpecialWorkspaceChanged);
+        })
+    });
+
+    let tx_clone = tx.clone();
+    event_listener.add_special_removed_handler(move |_| {
+        let tx = tx_clone.clone();
+        Box::pin(async move {
+            let _ = tx.send(WindowEvent::SpecialWorkspaceChanged);
+        })
+    });
+
+    event_listener
+}
+
+fn spawn_event_listener() -> mpsc::Receiver<WindowEvent> {
     let (tx, rx) = mpsc::channel();
 
     thread::spawn(move || {
-        let rt = tokio::runtime::Builder::new_current_thread()
-            .enable_all()
-            .build()
-            .unwrap();
-
-        rt.block_on(async {
-            let mut event_listener = AsyncEventListener::new();
-
-            let tx_clone = tx.clone();
-            event_listener.add_window_opened_handler(move |_| {
-                let tx = tx_clone.clone();
-                Box::pin(async move {
-                    let _ = tx.send(HyprlandEvent::WindowsChanged);
-                })
-            });
+        // A panic in here would otherwise end the thread without a word. Dropping `tx` on the
+        // way out is what tells the overlay to start a new listener.
+        let listened = panic::catch_unwind(AssertUnwindSafe(|| listen(tx)));
+        if let Err(payload) = listened {
+            eprintln!(
+                "hyprsnow: Hyprland event listener crashed: {}",
+                panic_message(&payload)
+            );
+        }
+    });
 
-            let tx_clone = tx.clone();
-            event_listener.add_window_closed_handler(move |_| {
-                let tx = tx_clone.clone();
-                Box::pin(async move {
-                    let _ = tx.send(HyprlandEvent::WindowsChanged);
-                })
-            });
+    rx
+}
 
-            let tx_clone = tx.clone();
-            event_listener.add_window_moved_handler(move |_| {
-                let tx = tx_clone.clone();
-                Box::pin(async move {
-                    let _ = tx.send(HyprlandEvent::WindowsChanged);
-                })
-            });
+/// Forwards Hyprland events to `tx` until the receiving end goes away.
+fn listen(tx: mpsc::Sender<WindowEvent>) {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
 
-            let tx_clone = tx.clone();
-            event_listener.add_active_window_changed_handler(move |_| {
-                let tx = tx_clone.clone();
-                Box::pin(async move {
-                    let _ = tx.send(HyprlandEvent::WindowsChanged);
-                })
-            });
+    rt.block_on(async {
+        let mut backoff = RECONNECT_MIN;
 
-            let tx_clone = tx.clone();
-            event_listener.add_workspace_changed_handler(move |_| {
-                let tx = tx_clone.clone();
-                Box::pin(async move {
-                    let _ = tx.send(HyprlandEvent::WindowsChanged);
-                })
-            });
+        // The socket goes away whenever Hyprland restarts, so keep reconnecting rather
+        // than letting window tracking stop for good
+        loop {
+            let connected_at = Instant::now();
+            match build_listener(&tx).start_listener_async().await {
+                Ok(()) => eprintln!("hyprsnow: Hyprland event stream closed"),
+                Err(e) => eprintln!("hyprsnow: Lost Hyprland IPC: {}", e),
+            }
 
-            let tx_clone = tx.clone();
-            event_listener.add_fullscreen_state_changed_handler(move |_| {
-                let tx = tx_clone.clone();
-                Box::pin(async move {
-                    let _ = tx.send(HyprlandEvent::WindowsChanged);
-                })
-            });
+            if tx.send(WindowEvent::Disconnected).is_err() {
+                break;
+            }
 
-            let _ = event_listener.start_listener_async().await;
-        });
+            // A listener that ran for a while was a healthy connection; start over
+            if connected_at.elapsed() > RECONNECT_MAX {
+                backoff = RECONNECT_MIN;
+            }
+
+            loop {
+                thread::sleep(backoff);
+                backoff = (backoff * 2).min(RECONNECT_MAX);
+                if Monitors::get().is_ok() {
+                    break;
+                }
+            }
+
+            eprintln!("hyprsnow: Hyprland IPC restored");
+            if tx.send(WindowEvent::Reconnected).is_err() {
+                break;
+            }
+        }
     });
+}
 
-    rx
+/// The text a panic was raised with, if it carried one.
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+/// Windows, monitors and events of the running Hyprland instance, over its IPC sockets.
+pub struct HyprlandSource;
+
+impl WindowSource for HyprlandSource {
+    fn bounds(&self) -> Rectangle {
+        let (min_x, min_y, max_x, max_y) = get_total_screen_bounds();
+        Rectangle::new(
+            Point::new(min_x, min_y),
+            Size::new(max_x - min_x, max_y - min_y),
+        )
+    }
+
+    fn windows(&self) -> Vec<WindowRect> {
+        get_hyprland_windows()
+    }
+
+    fn layer_surfaces(&self) -> Vec<WindowRect> {
+        get_layer_surfaces()
+    }
+
+    fn monitors(&self) -> Vec<MonitorRect> {
+        get_monitors_with_fullscreen_state()
+    }
+
+    fn active_window(&self) -> Option<Address> {
+        get_active_window_address()
+    }
+
+    fn cursor_position(&self) -> Option<(f32, f32)> {
+        get_cursor_position()
+    }
+
+    fn events(&mut self) -> mpsc::Receiver<WindowEvent> {
+        spawn_event_listener()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn addresses<'a>(windows: impl Iterator<Item = &'a WindowRect>) -> Vec<String> {
+        windows.map(|w| w.address.to_string()).collect()
+    }
+
+    #[test]
+    fn window_index_matches_a_linear_scan() {
+        let mut rng = StdRng::seed_from_u64(19);
+        let windows: Vec<WindowRect> = (0..200)
+            .map(|i| WindowRect {
+                address: Address::new(format!("0x{:x}", i)),
+                class: String::new(),
+                title: String::new(),
+                x: rng.random_range(-500.0..3000.0),
+                y: rng.random_range(0.0..1000.0),
+                width: rng.random_range(0.0..800.0),
+                height: 100.0,
+                floating: false,
+            })
+            .collect();
+        let index = WindowIndex::new(windows.clone());
+
+        // Random spots, plus column boundaries and the window edges straddling them
+        let mut xs: Vec<f32> = (0..2000)
+            .map(|_| rng.random_range(-600.0..4000.0))
+            .collect();
+        xs.extend((-5..35).map(|c| index.origin + c as f32 * INDEX_COLUMN_WIDTH));
+        xs.extend(windows.iter().flat_map(|w| [w.x, w.x + w.width]));
+
+        for x in xs {
+            let linear = windows.iter().filter(|w| x >= w.x && x <= w.x + w.width);
+            assert_eq!(addresses(index.at_x(x)), addresses(linear), "at x = {}", x);
+        }
+    }
 }
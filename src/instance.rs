@@ -0,0 +1,84 @@
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long `--replace` waits for the old instance to exit before giving up; it fades its
+/// snow out before exiting
+const REPLACE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Marks this process as the running instance; the PID file is removed again on drop.
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        // Only clean up if a newer instance hasn't taken the file over in the meantime
+        if read_pid(&self.path) == Some(std::process::id() as i32) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+fn pid_file_path() -> PathBuf {
+    let runtime_dir = std::env::var("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| std::env::temp_dir());
+    runtime_dir.join("hyprsnow.pid")
+}
+
+fn read_pid(path: &PathBuf) -> Option<i32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Whether `pid` is a live hyprsnow process, so stale PID files left by a crash or a reused
+/// PID don't block startup.
+fn is_hyprsnow(pid: i32) -> bool {
+    fs::read_to_string(format!("/proc/{}/comm", pid))
+        .map(|comm| comm.trim() == "hyprsnow")
+        .unwrap_or(false)
+}
+
+fn stop(pid: i32) -> Result<(), String> {
+    // SAFETY: kill has no memory-safety preconditions
+    if unsafe { libc::kill(pid, libc::SIGTERM) } != 0 {
+        return Err(format!(
+            "failed to stop running instance (pid {}): {}",
+            pid,
+            std::io::Error::last_os_error()
+        ));
+    }
+
+    let start = Instant::now();
+    while is_hyprsnow(pid) {
+        if start.elapsed() > REPLACE_TIMEOUT {
+            return Err(format!("running instance (pid {}) did not exit", pid));
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    Ok(())
+}
+
+/// Claims the PID file, stopping an already running instance first when `replace` is set.
+pub fn acquire(replace: bool) -> Result<PidFile, String> {
+    let path = pid_file_path();
+
+    if let Some(pid) = read_pid(&path)
+        && pid != std::process::id() as i32
+        && is_hyprsnow(pid)
+    {
+        if !replace {
+            return Err(format!(
+                "already running (pid {}), use --replace to restart it",
+                pid
+            ));
+        }
+        stop(pid)?;
+    }
+
+    fs::write(&path, format!("{}\n", std::process::id()))
+        .map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+
+    Ok(PidFile { path })
+}
@@ -0,0 +1,125 @@
+use crate::config::SnowConfig;
+use iced::futures::channel::mpsc;
+use iced::futures::{SinkExt, Stream, StreamExt};
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::thread;
+
+#[derive(Debug, Clone)]
+pub enum ControlCommand {
+    Pause,
+    Resume,
+    TogglePause,
+    /// Sets a `general` config option, e.g. `set intensity 7`
+    Set {
+        key: String,
+        value: String,
+    },
+}
+
+fn socket_path() -> PathBuf {
+    let runtime_dir = std::env::var("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| std::env::temp_dir());
+    runtime_dir.join("hyprsnow.sock")
+}
+
+/// Removes the control socket on the way out, so none is left behind in the runtime directory.
+pub fn remove_socket() {
+    let _ = fs::remove_file(socket_path());
+}
+
+fn parse_command(line: &str) -> Result<ControlCommand, String> {
+    let mut words = line.split_whitespace();
+    let command = match words.next() {
+        Some("pause") => ControlCommand::Pause,
+        Some("resume") => ControlCommand::Resume,
+        Some("toggle") => ControlCommand::TogglePause,
+        Some("set") => {
+            let key = words.next().ok_or("usage: set <option> <value>")?;
+            let value = words.collect::<Vec<_>>().join(" ");
+            if value.is_empty() {
+                return Err("usage: set <option> <value>".to_string());
+            }
+            // Validate here so the client gets the error instead of it going to stderr
+            SnowConfig::default().set(key, &value)?;
+            return Ok(ControlCommand::Set {
+                key: key.to_string(),
+                value,
+            });
+        }
+        Some(other) => return Err(format!("unknown command {:?}", other)),
+        None => return Err("empty command".to_string()),
+    };
+
+    match words.next() {
+        Some(_) => Err("unexpected arguments".to_string()),
+        None => Ok(command),
+    }
+}
+
+fn serve_client(stream: UnixStream, tx: &mpsc::UnboundedSender<ControlCommand>) {
+    let mut writer = match stream.try_clone() {
+        Ok(w) => w,
+        Err(_) => return,
+    };
+
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else { break };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let reply = match parse_command(&line) {
+            Ok(command) => {
+                if tx.unbounded_send(command).is_err() {
+                    return;
+                }
+                "ok".to_string()
+            }
+            Err(e) => format!("error: {}", e),
+        };
+
+        if writeln!(writer, "{}", reply).is_err() {
+            break;
+        }
+    }
+}
+
+/// Stream of commands sent to the control socket, accepted on a blocking listener thread.
+pub fn control_stream() -> impl Stream<Item = ControlCommand> {
+    iced::stream::channel(8, async |mut output| {
+        let (tx, mut rx) = mpsc::unbounded();
+
+        thread::spawn(move || {
+            let path = socket_path();
+            // Left behind by an instance that didn't exit cleanly; the PID file already
+            // guarantees nobody else is serving it
+            let _ = fs::remove_file(&path);
+
+            let listener = match UnixListener::bind(&path) {
+                Ok(l) => l,
+                Err(e) => {
+                    eprintln!(
+                        "hyprsnow: Failed to open control socket {}: {}",
+                        path.display(),
+                        e
+                    );
+                    return;
+                }
+            };
+
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else { continue };
+                let tx = tx.clone();
+                thread::spawn(move || serve_client(stream, &tx));
+            }
+        });
+
+        while let Some(command) = rx.next().await {
+            let _ = output.send(command).await;
+        }
+    })
+}
@@ -0,0 +1,24 @@
+//! Falling snow for iced, plus the pieces that run it as a Hyprland overlay.
+//!
+//! [`Waysnow`] is the simulation. It learns about windows and monitors from a [`WindowSource`],
+//! moves on with [`Waysnow::step`] and paints onto any canvas frame with
+//! [`Waysnow::draw_into`], so it can live inside another iced app. The `hyprsnow` binary is
+//! one such app, drawing on a layer-shell surface.
+
+pub mod cli;
+pub mod config;
+mod hyprland;
+mod ipc;
+mod pile;
+mod signals;
+pub mod snow;
+mod source;
+mod wind;
+
+/// Identifies a window in [`WindowRect`] and [`WindowEvent`], so a custom source doesn't need
+/// its own dependency on `hyprland`
+pub use ::hyprland::shared::Address;
+pub use config::SnowConfig;
+pub use hyprland::{HyprlandSource, MonitorRect, WindowRect};
+pub use snow::Waysnow;
+pub use source::{NullSource, WindowEvent, WindowSource};
@@ -1,29 +1,101 @@
-mod cli;
-mod config;
-mod hyprland;
-mod snow;
+mod autostart;
+mod instance;
+mod snapshot;
 
 use clap::Parser;
+use hyprsnow::{cli, config, snow};
 use iced_layershell::reexport::{Anchor, KeyboardInteractivity, Layer};
 use iced_layershell::settings::LayerShellSettings;
 
 fn main() -> Result<(), iced_layershell::Error> {
     let args = cli::Args::parse();
-    let mut config = config::load_config();
+
+    if let Some(cli::Command::Config) = args.command {
+        let mut config = config::load_config(args.mode);
+        config::apply_cli_overrides(&mut config, &args);
+        print!("{}", config);
+        return Ok(());
+    }
+
+    if let Some(cli::Command::Init) = args.command {
+        match config::write_default_config() {
+            Ok(path) => println!("Wrote {}", path.display()),
+            Err(e) => {
+                eprintln!("hyprsnow: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(cli::Command::InstallAutostart { exec_once }) = args.command {
+        let result = if exec_once {
+            autostart::exec_once_line().map(|line| println!("{}", line))
+        } else {
+            autostart::install().map(|path| println!("Wrote {}", path.display()))
+        };
+        if let Err(e) = result {
+            eprintln!("hyprsnow: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Some(path) = &args.snapshot {
+        let mut config = config::load_config(args.mode);
+        config::apply_cli_overrides(&mut config, &args);
+        if let Err(e) = snapshot::snapshot(config, args.width, args.height, args.warmup, path) {
+            eprintln!("hyprsnow: Snapshot failed: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let _pid_file = match instance::acquire(args.replace) {
+        Ok(pid_file) => pid_file,
+        Err(e) => {
+            eprintln!("hyprsnow: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut config = config::load_config(args.mode);
     config::apply_cli_overrides(&mut config, &args);
 
+    // With `region` the surface is a band along one edge, stretched to the full width
+    let anchor = match (config.region, config.anchor) {
+        (0, _) => Anchor::Top | Anchor::Bottom | Anchor::Left | Anchor::Right,
+        (_, config::RegionAnchor::Top) => Anchor::Top | Anchor::Left | Anchor::Right,
+        (_, config::RegionAnchor::Bottom) => Anchor::Bottom | Anchor::Left | Anchor::Right,
+    };
+
     let layer_settings = LayerShellSettings {
-        size: Some((0, 0)),
+        size: Some((0, config.region)),
         exclusive_zone: -1,
-        anchor: Anchor::Top | Anchor::Bottom | Anchor::Left | Anchor::Right,
-        layer: Layer::Overlay,
-        keyboard_interactivity: KeyboardInteractivity::None,
+        anchor,
+        layer: match config.shell_layer {
+            config::ShellLayer::Overlay => Layer::Overlay,
+            config::ShellLayer::Top => Layer::Top,
+            config::ShellLayer::Bottom => Layer::Bottom,
+            config::ShellLayer::Background => Layer::Background,
+        },
+        // The surface lets clicks through, so it can't be clicked to get focus on demand;
+        // `--interactive` holds the keyboard for as long as hyprsnow runs instead
+        keyboard_interactivity: if args.interactive {
+            KeyboardInteractivity::Exclusive
+        } else {
+            KeyboardInteractivity::None
+        },
         events_transparent: true,
         ..Default::default()
     };
 
-    iced_layershell::application(move || snow::boot(config.clone()), "hyprsnow", snow::update, snow::view)
-        .antialiasing(false)
+    // Soft edges rely on antialiasing; the renderer can't switch it on a later reload
+    let antialiasing = config.soft_edges;
+
+    let boot = move || snow::boot(config.clone(), args.clone());
+    iced_layershell::application(boot, "hyprsnow", snow::update, snow::view)
+        .antialiasing(antialiasing)
         .style(|_state, _theme| iced::theme::Style {
             background_color: iced::Color::TRANSPARENT,
             text_color: iced::Color::WHITE,
@@ -0,0 +1,321 @@
+use iced::Point;
+use iced::widget::canvas::Path;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Horizontal resolution of the pile in pixels per bucket
+const BUCKET_WIDTH: f32 = 4.0;
+/// Fraction of a flake's area that ends up as packed snow on the pile
+const PACKING: f32 = 0.2;
+/// Steepest allowed height difference between neighbouring buckets before snow slides off
+const MAX_STEP: f32 = BUCKET_WIDTH;
+/// Fraction of a bucket's snow blown into the next bucket per second for each pixel/second of
+/// wind
+const DRIFT_RATE: f32 = 0.002;
+/// Most a bucket may lose to the wind in one step, which keeps the transport stable at low
+/// frame rates and in storms
+const MAX_DRIFT_FRACTION: f32 = 0.05;
+
+/// Snow accumulated along the bottom of the screen, stored as one height per bucket.
+pub struct SnowPile {
+    heights: Vec<f32>,
+    max_height: f32,
+}
+
+impl SnowPile {
+    pub fn new(width: f32, max_height: f32) -> Self {
+        let buckets = (width / BUCKET_WIDTH).ceil().max(1.0) as usize;
+        Self {
+            heights: vec![0.0; buckets],
+            max_height: max_height.max(0.0),
+        }
+    }
+
+    pub fn set_max_height(&mut self, max_height: f32) {
+        self.max_height = max_height.max(0.0);
+        for h in &mut self.heights {
+            *h = h.min(self.max_height);
+        }
+    }
+
+    fn bucket_at(&self, x: f32) -> usize {
+        ((x / BUCKET_WIDTH).max(0.0) as usize).min(self.heights.len() - 1)
+    }
+
+    /// Height of the pile under `x`, in pixels above the bottom edge.
+    pub fn height_at(&self, x: f32) -> f32 {
+        self.heights[self.bucket_at(x)]
+    }
+
+    /// Adds a settled flake to the pile, spreading it over the buckets it covers.
+    pub fn deposit(&mut self, x: f32, radius: f32) {
+        if self.max_height <= 0.0 {
+            return;
+        }
+
+        let first = self.bucket_at(x - radius);
+        let last = self.bucket_at(x + radius);
+        let covered = (last - first + 1) as f32 * BUCKET_WIDTH;
+        let share = std::f32::consts::PI * radius * radius * PACKING / covered;
+
+        for h in &mut self.heights[first..=last] {
+            *h = (*h + share).min(self.max_height);
+        }
+
+        self.settle(first, last);
+    }
+
+    /// Carries snow downwind by `wind_x * dt` worth of transport, so sustained wind heaps it up
+    /// against the leeward screen edge.
+    pub fn blow(&mut self, wind_x: f32, dt: f32) {
+        let fraction = (wind_x.abs() * DRIFT_RATE * dt).min(MAX_DRIFT_FRACTION);
+        if fraction <= 0.0 || self.heights.len() < 2 {
+            return;
+        }
+
+        // Walking against the wind moves each bucket's snow only once per step
+        let last = self.heights.len() - 1;
+        for step in 0..last {
+            let (from, to) = if wind_x > 0.0 {
+                (last - 1 - step, last - step)
+            } else {
+                (step + 1, step)
+            };
+            let moved = (self.heights[from] * fraction).min(self.max_height - self.heights[to]);
+            if moved > 0.0 {
+                self.heights[from] -= moved;
+                self.heights[to] += moved;
+            }
+        }
+
+        self.settle(0, last);
+    }
+
+    /// Lets snow slide from steep spots into neighbouring buckets so the pile stays smooth.
+    fn settle(&mut self, first: usize, last: usize) {
+        let lo = first.saturating_sub(8);
+        let hi = (last + 8).min(self.heights.len() - 1);
+
+        for _ in 0..4 {
+            for i in lo..hi {
+                let diff = self.heights[i] - self.heights[i + 1];
+                if diff.abs() > MAX_STEP {
+                    let moved = (diff.abs() - MAX_STEP) / 2.0 * diff.signum();
+                    self.heights[i] -= moved;
+                    self.heights[i + 1] += moved;
+                }
+            }
+        }
+    }
+
+    /// Builds filled outlines of the pile above `bottom`, starting at `left`, one per run of
+    /// visible buckets.
+    pub fn paths(&self, left: f32, bottom: f32, is_visible: impl Fn(f32) -> bool) -> Vec<Path> {
+        let mut paths = Vec::new();
+        let mut run: Vec<Point> = Vec::new();
+
+        for (i, h) in self.heights.iter().enumerate() {
+            let x = left + (i as f32 + 0.5) * BUCKET_WIDTH;
+            if *h > 0.0 && is_visible(x) {
+                run.push(Point::new(x, bottom - h));
+            } else if !run.is_empty() {
+                paths.push(Self::outline(&run, bottom));
+                run.clear();
+            }
+        }
+
+        if !run.is_empty() {
+            paths.push(Self::outline(&run, bottom));
+        }
+
+        paths
+    }
+
+    fn outline(points: &[Point], bottom: f32) -> Path {
+        Path::new(|builder| {
+            let half = BUCKET_WIDTH / 2.0;
+            builder.move_to(Point::new(points[0].x - half, bottom));
+            for point in points {
+                builder.line_to(*point);
+            }
+            builder.line_to(Point::new(points[points.len() - 1].x + half, bottom));
+            builder.close();
+        })
+    }
+}
+
+/// Saved piles older than this are considered stale, and the snow starts over
+const SAVED_MAX_AGE: Duration = Duration::from_secs(10 * 60);
+
+fn saved_piles_path() -> PathBuf {
+    let runtime_dir = std::env::var("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| std::env::temp_dir());
+    runtime_dir.join("hyprsnow.piles")
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// A monitor's bottom edge in overlay coordinates, where its pile rests.
+#[derive(Clone, PartialEq)]
+pub struct GroundEdge {
+    pub name: String,
+    pub left: f32,
+    pub width: f32,
+    pub bottom: f32,
+}
+
+/// Snow piled along the bottom edge of every monitor. Each monitor keeps its own pile, so
+/// accumulation survives workspace switches and stays put when other monitors come and go.
+/// Windows never affect the piles: snow landing on a window melts there, and a window
+/// covering a pile simply hides it until it moves away.
+pub struct Ground {
+    piles: Vec<(GroundEdge, SnowPile)>,
+    max_height: f32,
+}
+
+impl Ground {
+    pub fn new(max_height: f32) -> Self {
+        Self {
+            piles: Vec::new(),
+            max_height,
+        }
+    }
+
+    pub fn set_max_height(&mut self, max_height: f32) {
+        self.max_height = max_height;
+        for (_, pile) in &mut self.piles {
+            pile.set_max_height(max_height);
+        }
+    }
+
+    /// Follows a new monitor layout, keeping the piles of monitors whose edge didn't change.
+    pub fn sync(&mut self, edges: Vec<GroundEdge>) {
+        let mut old = std::mem::take(&mut self.piles);
+        self.piles = edges
+            .into_iter()
+            .map(|edge| match old.iter().position(|(e, _)| *e == edge) {
+                Some(i) => old.swap_remove(i),
+                None => {
+                    let pile = SnowPile::new(edge.width, self.max_height);
+                    (edge, pile)
+                }
+            })
+            .collect();
+    }
+
+    /// The pile a flake at `x` falls onto: the lowest monitor edge spanning `x`, since snow
+    /// passes from one vertically stacked monitor into the next.
+    fn pile_at(&self, x: f32) -> Option<usize> {
+        self.piles
+            .iter()
+            .enumerate()
+            .filter(|(_, (edge, _))| x >= edge.left && x < edge.left + edge.width)
+            .max_by(|(_, (a, _)), (_, (b, _))| a.bottom.total_cmp(&b.bottom))
+            .map(|(i, _)| i)
+    }
+
+    /// Y coordinate of the snow surface under `x`, or `None` where no monitor is below.
+    pub fn surface_at(&self, x: f32) -> Option<f32> {
+        let (edge, pile) = &self.piles[self.pile_at(x)?];
+        Some(edge.bottom - pile.height_at(x - edge.left))
+    }
+
+    pub fn deposit(&mut self, x: f32, radius: f32) {
+        if let Some(i) = self.pile_at(x) {
+            let (edge, pile) = &mut self.piles[i];
+            pile.deposit(x - edge.left, radius);
+        }
+    }
+
+    /// Blows every pile along the wind; snow stops at the monitor edge instead of crossing it.
+    pub fn blow(&mut self, wind_x: f32, dt: f32) {
+        for (_, pile) in &mut self.piles {
+            pile.blow(wind_x, dt);
+        }
+    }
+
+    /// Writes the piles to the runtime directory, so a restart can carry on with the same snow.
+    /// The file holds the time of saving, then one line per monitor edge with its heights.
+    pub fn save(&self) {
+        let mut text = format!("{}\n", unix_time());
+        for (edge, pile) in &self.piles {
+            let heights: Vec<String> = pile.heights.iter().map(|h| h.to_string()).collect();
+            text += &format!(
+                "{}\t{}\t{}\t{}\t{}\n",
+                edge.name,
+                edge.left,
+                edge.width,
+                edge.bottom,
+                heights.join(" ")
+            );
+        }
+
+        let path = saved_piles_path();
+        if let Err(e) = fs::write(&path, text) {
+            eprintln!(
+                "hyprsnow: Failed to save snow piles to {}: {}",
+                path.display(),
+                e
+            );
+        }
+    }
+
+    /// Refills the piles from the last `save`, unless it is stale. Piles whose monitor edge
+    /// moved or resized since start out empty.
+    pub fn restore(&mut self) {
+        let Ok(text) = fs::read_to_string(saved_piles_path()) else {
+            return;
+        };
+        let mut lines = text.lines();
+        let saved_at: u64 = match lines.next().and_then(|l| l.parse().ok()) {
+            Some(t) => t,
+            None => return,
+        };
+        if unix_time().saturating_sub(saved_at) > SAVED_MAX_AGE.as_secs() {
+            return;
+        }
+
+        let same = |field: &str, value: f32| field.parse::<f32>() == Ok(value);
+        for line in lines {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [name, left, width, bottom, heights] = fields[..] else {
+                continue;
+            };
+            let Some((_, pile)) = self.piles.iter_mut().find(|(edge, _)| {
+                edge.name == name
+                    && same(left, edge.left)
+                    && same(width, edge.width)
+                    && same(bottom, edge.bottom)
+            }) else {
+                continue;
+            };
+
+            let heights: Vec<f32> = heights
+                .split_whitespace()
+                .filter_map(|h| h.parse().ok())
+                .collect();
+            if heights.len() == pile.heights.len() {
+                pile.heights = heights
+                    .into_iter()
+                    .map(|h| h.clamp(0.0, pile.max_height))
+                    .collect();
+            }
+        }
+    }
+
+    /// Outlines of every pile, skipping spots where `is_visible(x, y)` says no.
+    pub fn paths(&self, is_visible: impl Fn(f32, f32) -> bool) -> Vec<Path> {
+        self.piles
+            .iter()
+            .flat_map(|(edge, pile)| {
+                pile.paths(edge.left, edge.bottom, |x| is_visible(x, edge.bottom - 1.0))
+            })
+            .collect()
+    }
+}
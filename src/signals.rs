@@ -0,0 +1,44 @@
+use iced::futures::channel::mpsc;
+use iced::futures::{SinkExt, Stream, StreamExt};
+use signal_hook::consts::{SIGINT, SIGTERM, SIGUSR1};
+use signal_hook::iterator::Signals;
+use std::thread;
+
+#[derive(Debug, Clone)]
+pub enum SignalEvent {
+    TogglePause,
+    /// SIGTERM or SIGINT: let the snow die down, then exit
+    Shutdown,
+}
+
+/// Stream of Unix signals hyprsnow reacts to, fed from a blocking signal thread.
+pub fn signal_stream() -> impl Stream<Item = SignalEvent> {
+    iced::stream::channel(8, async |mut output| {
+        let (tx, mut rx) = mpsc::unbounded();
+
+        thread::spawn(move || {
+            let mut signals = match Signals::new([SIGUSR1, SIGTERM, SIGINT]) {
+                Ok(s) => s,
+                Err(e) => {
+                    eprintln!("hyprsnow: Failed to register signal handlers: {}", e);
+                    return;
+                }
+            };
+
+            for signal in signals.forever() {
+                let event = match signal {
+                    SIGUSR1 => SignalEvent::TogglePause,
+                    SIGTERM | SIGINT => SignalEvent::Shutdown,
+                    _ => continue,
+                };
+                if tx.unbounded_send(event).is_err() {
+                    break;
+                }
+            }
+        });
+
+        while let Some(event) = rx.next().await {
+            let _ = output.send(event).await;
+        }
+    })
+}
@@ -0,0 +1,39 @@
+use hyprsnow::{SnowConfig, Waysnow};
+use iced::advanced::graphics::geometry::Renderer as _;
+use iced::advanced::renderer::Headless;
+use iced::mouse::Cursor;
+use iced::widget::canvas::Program;
+use iced::{Color, Font, Pixels, Rectangle, Renderer, Size, Theme};
+use std::path::Path;
+
+/// Lets the snow fall for `warmup` seconds without a layer-shell surface, then saves what a
+/// `width`x`height` overlay would show at that point as a PNG.
+pub fn snapshot(
+    config: SnowConfig,
+    width: u32,
+    height: u32,
+    warmup: f32,
+    path: &Path,
+) -> Result<(), String> {
+    let mut state = Waysnow::offscreen(config, width as f32, height as f32);
+    state.simulate(warmup);
+
+    // The software renderer needs no GPU, so this also works over SSH or in CI
+    let mut renderer = iced::futures::executor::block_on(Renderer::new(
+        Font::default(),
+        Pixels(16.0),
+        Some("tiny-skia"),
+    ))
+    .ok_or("failed to create the software renderer")?;
+
+    let bounds = Rectangle::with_size(Size::new(width as f32, height as f32));
+    for geometry in (&state).draw(&(), &renderer, &Theme::Dark, bounds, Cursor::Unavailable) {
+        renderer.draw_geometry(geometry);
+    }
+    let pixels = renderer.screenshot(Size::new(width, height), 1.0, Color::TRANSPARENT);
+
+    image::RgbaImage::from_raw(width, height, pixels)
+        .ok_or("the renderer returned a frame of the wrong size")?
+        .save(path)
+        .map_err(|e| format!("failed to write {}: {}", path.display(), e))
+}
@@ -1,15 +1,29 @@
-use crate::config::{ConfigEvent, SnowConfig, spawn_config_watcher};
-use crate::hyprland::{
-    MonitorRect, WindowRect, get_hyprland_windows, get_monitors_with_fullscreen_state,
-    get_total_screen_bounds, spawn_event_listener,
+use crate::cli::Args;
+use crate::config::{
+    ConfigEvent, FlakeShape, LayerMode, MeltCurve, OrphanBehavior, ParticleMode, RegionAnchor,
+    SnowConfig, SuppressOn, spawn_config_watcher,
 };
+use crate::hyprland::{HyprlandSource, MonitorRect, WindowIndex, WindowRect};
+use crate::ipc::{ControlCommand, control_stream, remove_socket};
+use crate::pile::{Ground, GroundEdge};
+use crate::signals::{SignalEvent, signal_stream};
+use crate::source::{NullSource, WindowEvent, WindowSource};
+use crate::wind::Wind;
 use hyprland::shared::Address;
-use iced::widget::image::Handle as ImageHandle;
+use iced::keyboard::{self, Key, key};
 use iced::mouse::Cursor;
-use iced::widget::canvas::{self, Canvas, Frame, Geometry, Path};
-use iced::{Color, Element, Length, Point, Rectangle, Renderer, Subscription, Task, Theme};
+use iced::widget::canvas::{self, Canvas, Frame, Geometry, Image, Path, gradient, path};
+use iced::widget::image::Handle as ImageHandle;
+use iced::widget::{container, stack, text};
+use iced::{
+    Color, Element, Length, Point, Radians, Rectangle, Renderer, Size, Subscription, Task, Theme,
+    Vector,
+};
 use iced_layershell::to_layer_message;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::cmp::Ordering;
+use std::path::PathBuf;
 use std::sync::mpsc;
 use std::time::{Duration, Instant};
 
@@ -18,314 +32,2007 @@ enum SnowState {
     Falling,
     Landed {
         melt_timer: f32,
+        /// Seconds this flake takes to melt, fixed when it lands
+        melt_duration: f32,
         window_addr: Option<Address>,
         offset_x: f32,
+        /// Width of the window when last seen, to tell resizes apart from moves
+        window_width: f32,
     },
 }
 
+/// Opacity multiplier for behind-layer flakes while a window covers them
+const BEHIND_DIM: f32 = 0.35;
+/// Seconds a behind-layer flake takes to dim when passing behind a window, and to recover
+const BEHIND_FADE_SECONDS: f32 = 0.3;
+/// Flakes fainter than this are skipped when drawing, since they can't be seen anyway
+const MIN_VISIBLE_OPACITY: f32 = 0.01;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FlakeLayer {
+    Front,
+    Behind,
+}
+
+impl FlakeLayer {
+    fn random(mode: LayerMode, rng: &mut impl Rng) -> Self {
+        match mode {
+            LayerMode::Front => FlakeLayer::Front,
+            LayerMode::Behind => FlakeLayer::Behind,
+            LayerMode::Both => {
+                if rng.random_bool(0.5) {
+                    FlakeLayer::Front
+                } else {
+                    FlakeLayer::Behind
+                }
+            }
+        }
+    }
+}
+
 struct Snowflake {
     x: f32,
     y: f32,
+    /// Where the flake (re)appeared, for fading it in over its first pixels of fall
+    spawn_y: f32,
     radius: f32,
     speed: f32,
+    /// Current horizontal velocity from drift and wind
+    vx: f32,
+    /// Current fall velocity; equals `speed` unless gravity is enabled
+    vy: f32,
+    /// Sway phase in radians, advanced by `drift_freq` and wrapped to 0..TAU
     phase: f32,
+    /// Seconds since the flake last (re)spawned
+    age: f32,
+    drift_freq: f32,
     drift_amount: f32,
     opacity: f32,
+    /// Opacity multiplier easing towards `BEHIND_DIM` while a behind-layer flake is covered
+    shade: f32,
+    /// Opacity left while the flake fades out as surplus to the target count; None otherwise
+    retiring: Option<f32>,
+    rotation: f32,
+    spin: f32,
+    color: Color,
     state: SnowState,
+    layer: FlakeLayer,
     image_index: Option<usize>,
+    /// Box of the monitor the flake last spawned over, which it stays on with `per_monitor_wrap`
+    home: Rectangle,
+    /// Number of flakes clumped together into this one; 1 for a lone flake
+    clump_size: usize,
+    shape: FlakeShape,
+}
+
+/// Fraction of a landed flake's opacity left after `progress` (0..1) of its melt.
+fn melt_opacity(curve: MeltCurve, progress: f32) -> f32 {
+    let progress = progress.clamp(0.0, 1.0);
+    match curve {
+        MeltCurve::Linear => 1.0 - progress,
+        MeltCurve::EaseOut => (1.0 - progress) * (1.0 - progress),
+        MeltCurve::Instant => {
+            if progress < 1.0 {
+                1.0
+            } else {
+                0.0
+            }
+        }
+    }
+}
+
+fn seeded_rng(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_rng(&mut rand::rng()),
+    }
+}
+
+/// Samples `min..max`, or returns `min` when the range is empty
+fn random_between(min: f32, max: f32, rng: &mut impl Rng) -> f32 {
+    if max > min {
+        rng.random_range(min..max)
+    } else {
+        min
+    }
+}
+
+/// Fall speed a flake accelerates towards under gravity; bigger flakes fall faster, up to
+/// `speed_max` for the largest ones.
+fn terminal_velocity(radius: f32, config: &SnowConfig) -> f32 {
+    config.speed_min + (config.speed_max - config.speed_min) * size_fraction(radius, config)
+}
+
+/// Where `radius` sits in the configured size range, from 0 for the smallest flakes to 1
+fn size_fraction(radius: f32, config: &SnowConfig) -> f32 {
+    if config.size_max > config.size_min {
+        ((radius - config.size_min) / (config.size_max - config.size_min)).clamp(0.0, 1.0)
+    } else {
+        1.0
+    }
+}
+
+/// Speed factor for a flake that reaches a window top without sticking to it
+const GLANCE_SLOWDOWN: f32 = 0.8;
+
+/// Seconds a landed flake of this size takes to melt; bigger flakes last longer
+fn melt_time(radius: f32, config: &SnowConfig) -> f32 {
+    let spread = 2.0 * size_fraction(radius, config) - 1.0;
+    config.melt_duration * (1.0 + config.melt_size_variation * spread)
+}
+
+/// With parallax, bigger flakes read as nearer by falling faster
+fn random_speed(radius: f32, config: &SnowConfig, rng: &mut impl Rng) -> f32 {
+    if config.parallax {
+        terminal_velocity(radius, config)
+    } else {
+        random_between(config.speed_min, config.speed_max, rng)
+    }
+}
+
+/// With parallax, bigger flakes read as nearer by being brighter
+fn random_opacity(radius: f32, config: &SnowConfig, rng: &mut impl Rng) -> f32 {
+    let opacity = if config.parallax {
+        0.4 + 0.6 * size_fraction(radius, config)
+    } else {
+        rng.random_range(0.7..1.0)
+    };
+    opacity * config.max_opacity
+}
+
+/// How strongly vertical wind moves a flake: fully for the smallest ones, less for heavier ones
+fn buoyancy(radius: f32, config: &SnowConfig) -> f32 {
+    (config.size_min.max(0.5) / radius.max(0.5)).min(1.0)
+}
+
+/// Pixels above the snow surface over which falling flakes ease into it
+const SETTLE_DISTANCE: f32 = 12.0;
+/// Fraction of its fall speed a flake keeps right at the surface
+const SETTLE_MIN_SPEED: f32 = 0.3;
+
+/// Fall speed factor for a flake whose bottom is `gap` pixels above the snow surface, so it
+/// sinks in softly instead of stopping dead.
+fn settle_factor(gap: f32) -> f32 {
+    SETTLE_MIN_SPEED + (1.0 - SETTLE_MIN_SPEED) * (gap / SETTLE_DISTANCE).clamp(0.0, 1.0)
+}
+
+/// Distance in pixels within which the pointer disturbs falling flakes
+const CURSOR_RADIUS: f32 = 80.0;
+/// Speed in pixels/second at which a flake right under the pointer is pushed away
+const CURSOR_PUSH: f32 = 150.0;
+
+/// Velocity pushing a flake at `(x, y)` away from the pointer, fading out towards
+/// `CURSOR_RADIUS`.
+fn cursor_push(x: f32, y: f32, cursor: Point) -> (f32, f32) {
+    let (dx, dy) = (x - cursor.x, y - cursor.y);
+    let distance = dx.hypot(dy);
+    if distance >= CURSOR_RADIUS || distance <= f32::EPSILON {
+        return (0.0, 0.0);
+    }
+
+    let strength = CURSOR_PUSH * (1.0 - distance / CURSOR_RADIUS);
+    (dx / distance * strength, dy / distance * strength)
+}
+
+fn random_drift_freq(config: &SnowConfig, rng: &mut impl Rng) -> f32 {
+    config.drift_freq * rng.random_range(0.75..1.25)
+}
+
+/// Spin in radians/second sampled from the configured degrees/second range
+fn random_spin(config: &SnowConfig, rng: &mut impl Rng) -> f32 {
+    random_between(config.spin_min, config.spin_max, rng).to_radians()
+}
+
+fn random_color(config: &SnowConfig, rng: &mut impl Rng) -> Color {
+    if config.palette.is_empty() {
+        config.color
+    } else {
+        config.palette[rng.random_range(0..config.palette.len())]
+    }
+}
+
+fn random_shape(config: &SnowConfig, rng: &mut impl Rng) -> FlakeShape {
+    if config.shapes.is_empty() {
+        FlakeShape::Circle
+    } else {
+        config.shapes[rng.random_range(0..config.shapes.len())]
+    }
+}
+
+/// Sprite for a flake of this size; sprites are sorted small to large, so with
+/// `image_size_map` each one covers an equal slice of the size range.
+fn pick_image_index(
+    radius: f32,
+    config: &SnowConfig,
+    sprite_count: usize,
+    rng: &mut impl Rng,
+) -> Option<usize> {
+    if sprite_count == 0 {
+        None
+    } else if config.image_size_map {
+        let slot = (size_fraction(radius, config) * sprite_count as f32) as usize;
+        Some(slot.min(sprite_count - 1))
+    } else {
+        Some(rng.random_range(0..sprite_count))
+    }
+}
+
+/// Seconds a melted flake's wet mark takes to dry up
+const WET_MARK_SECONDS: f32 = 1.0;
+/// Most wet marks kept at once; the oldest dries instantly to make room
+const MAX_WET_MARKS: usize = 128;
+/// Radius and peak opacity of the darker spot a melted flake leaves behind
+const WET_MARK_RADIUS: f32 = 3.0;
+const WET_MARK_OPACITY: f32 = 0.15;
+
+/// Twinkle cycles per sway cycle; a whole number so the shimmer stays smooth where the sway
+/// phase wraps around
+const TWINKLE_RATE: f32 = 3.0;
+/// Lowest opacity factor a twinkling flake dims to
+const TWINKLE_MIN: f32 = 0.6;
+
+/// Opacity factor for a falling flake's shimmer, between `TWINKLE_MIN` and 1
+fn twinkle_factor(flake: &Snowflake) -> f32 {
+    let wave = (flake.phase * TWINKLE_RATE).sin() * 0.5 + 0.5;
+    TWINKLE_MIN + (1.0 - TWINKLE_MIN) * wave
+}
+
+/// Depth of the edge tint as a fraction of the monitor's shorter side
+const TINT_DEPTH: f32 = 0.2;
+/// Opacity of the edge tint at full `tint_strength`, low enough to keep the desktop readable
+const TINT_MAX_OPACITY: f32 = 0.3;
+
+/// Opacity steps landed snow is grouped into, so flakes that have melted about equally far
+/// merge into one shape
+const BLANKET_LEVELS: f32 = 16.0;
+
+/// Fills landed flakes as one shape per color instead of circle by circle, so overlapping
+/// flakes form a smooth blanket rather than darker and lighter speckles.
+fn draw_blanket(frame: &mut Frame, mut patches: Vec<(Color, Point, f32)>) {
+    for (color, ..) in &mut patches {
+        color.a = (color.a * BLANKET_LEVELS).round() / BLANKET_LEVELS;
+    }
+    patches.sort_by_key(|(color, ..)| color.into_rgba8());
+
+    for group in patches.chunk_by(|a, b| a.0 == b.0) {
+        if group[0].0.a < MIN_VISIBLE_OPACITY {
+            continue;
+        }
+        let shape = Path::new(|builder| {
+            for (_, center, radius) in group {
+                builder.circle(*center, *radius);
+            }
+        });
+        frame.fill(&shape, group[0].0);
+    }
+}
+
+/// How often a schedule may move the intensity by one level
+const SCHEDULE_STEP: Duration = Duration::from_secs(5);
+
+/// Minutes since local midnight, for `schedule`
+fn local_minute_of_day() -> u32 {
+    // SAFETY: localtime_r only writes to the `tm` it is given; a zeroed tm is a valid value
+    unsafe {
+        let now = libc::time(std::ptr::null_mut());
+        let mut tm: libc::tm = std::mem::zeroed();
+        if libc::localtime_r(&now, &mut tm).is_null() {
+            return 0;
+        }
+        (tm.tm_hour * 60 + tm.tm_min) as u32
+    }
+}
+
+/// How long the snow takes to fade away after SIGTERM or SIGINT before hyprsnow exits
+const DRAIN_TIME: Duration = Duration::from_secs(2);
+
+/// Seconds over which the flake count eases towards a changed target, e.g. after a reload
+const POPULATION_EASE_SECONDS: f32 = 1.0;
+
+/// Seconds a surplus flake takes to fade out before it is removed
+const RETIRE_SECONDS: f32 = 1.0;
+
+/// Fall time over which `startup_ramp` spreads the first flakes above the screen
+const STARTUP_RAMP_SECONDS: f32 = 3.0;
+
+/// Seconds of travel shown as a raindrop's streak, so faster drops draw longer lines
+const RAIN_STREAK: f32 = 0.03;
+
+/// Copies of a flake drawn behind it for `trail_strength`, spaced this many seconds of travel
+/// apart and fading towards the end of the trail
+const TRAIL_COPIES: usize = 4;
+const TRAIL_SPACING: f32 = 0.04;
+
+/// A thin vertical streak ending at the drop's position
+fn raindrop_path(flake: &Snowflake) -> Path {
+    let length = (flake.vy * RAIN_STREAK).max(flake.radius * 2.0);
+    Path::rectangle(
+        Point::new(flake.x - flake.radius / 2.0, flake.y - length),
+        Size::new(flake.radius, length),
+    )
+}
+
+/// Whether any part of the flake can reach the canvas. The reach covers a rotated sprite's
+/// corners and a raindrop's streak, which trails above the drop.
+fn is_on_canvas(flake: &Snowflake, bounds: Size) -> bool {
+    let reach =
+        (flake.radius * (std::f32::consts::SQRT_2 + CLUMP_SPREAD)).max(flake.vy * RAIN_STREAK);
+    flake.x + reach >= 0.0
+        && flake.x - reach <= bounds.width
+        && flake.y + reach >= 0.0
+        && flake.y - reach <= bounds.height
+}
+
+/// A pointed leaf outline, rotated to the flake's current tumble
+fn leaf_path(flake: &Snowflake) -> Path {
+    let (sin, cos) = flake.rotation.sin_cos();
+    let point = |along: f32, across: f32| {
+        Point::new(
+            flake.x + along * cos - across * sin,
+            flake.y + along * sin + across * cos,
+        )
+    };
+    let length = flake.radius;
+    let width = flake.radius * 0.5;
+
+    Path::new(|builder| {
+        builder.move_to(point(-length, 0.0));
+        builder.quadratic_curve_to(point(0.0, -width * 2.0), point(length, 0.0));
+        builder.quadratic_curve_to(point(0.0, width * 2.0), point(-length, 0.0));
+        builder.close();
+    })
+}
+
+/// Range of flakes that make up a clump
+const CLUMP_SIZES: std::ops::RangeInclusive<usize> = 3..=5;
+/// Distance of each flake in a clump from its middle, relative to the flake radius
+const CLUMP_SPREAD: f32 = 0.7;
+/// Fall speed factor for clumps, which are heavier than a lone flake
+const CLUMP_SPEEDUP: f32 = 1.25;
+
+fn clump_speedup(clump_size: usize) -> f32 {
+    if clump_size > 1 { CLUMP_SPEEDUP } else { 1.0 }
+}
+
+fn random_clump_size(config: &SnowConfig, rng: &mut impl Rng) -> usize {
+    if config.mode == ParticleMode::Snow && rng.random_bool(config.clump_chance as f64) {
+        rng.random_range(CLUMP_SIZES)
+    } else {
+        1
+    }
+}
+
+/// Centres of the circles a flake is drawn as: its position, or a ring around it for a clump,
+/// turned by the flake's rotation.
+fn flake_centers(flake: &Snowflake) -> impl Iterator<Item = Point> + '_ {
+    let spread = if flake.clump_size > 1 {
+        flake.radius * CLUMP_SPREAD
+    } else {
+        0.0
+    };
+    (0..flake.clump_size).map(move |i| {
+        let angle = flake.rotation + i as f32 * std::f32::consts::TAU / flake.clump_size as f32;
+        Point::new(
+            flake.x + spread * angle.cos(),
+            flake.y + spread * angle.sin(),
+        )
+    })
+}
+
+/// Radius of a star's inner corners relative to its points
+const STAR_INNER: f32 = 0.45;
+
+/// Adds a hexagon or six-pointed star of the given outer radius around `center`, turned by
+/// `rotation`.
+fn add_crystal(
+    builder: &mut path::Builder,
+    shape: FlakeShape,
+    center: Point,
+    radius: f32,
+    rotation: f32,
+) {
+    let corners = if shape == FlakeShape::Star { 12 } else { 6 };
+    for i in 0..corners {
+        let distance = if shape == FlakeShape::Star && i % 2 == 1 {
+            radius * STAR_INNER
+        } else {
+            radius
+        };
+        let angle = rotation + i as f32 * std::f32::consts::TAU / corners as f32;
+        let corner = Point::new(
+            center.x + distance * angle.cos(),
+            center.y + distance * angle.sin(),
+        );
+        if i == 0 {
+            builder.move_to(corner);
+        } else {
+            builder.line_to(corner);
+        }
+    }
+    builder.close();
+}
+
+/// Step in pixels that radii are rounded to when a circle is drawn from `CirclePaths`
+const CIRCLE_BUCKET: f32 = 0.25;
+
+/// Circles around the origin, one per `CIRCLE_BUCKET` of radius up to the largest flake, built
+/// once per config so drawing a flake moves a shared path instead of building a new one.
+struct CirclePaths(Vec<Path>);
+
+impl CirclePaths {
+    fn new(config: &SnowConfig) -> Self {
+        let max_radius = config.size_max.max(WET_MARK_RADIUS);
+        let buckets = (max_radius / CIRCLE_BUCKET).ceil() as usize;
+        Self(
+            (0..=buckets)
+                .map(|i| Path::circle(Point::ORIGIN, i as f32 * CIRCLE_BUCKET))
+                .collect(),
+        )
+    }
+
+    /// Fills a circle of about `radius` around `center`. Radii past the largest bucket, left
+    /// over from before `size_max` shrank, get a path of their own.
+    fn fill(&self, frame: &mut Frame, center: Point, radius: f32, color: Color) {
+        match self.0.get((radius / CIRCLE_BUCKET).round() as usize) {
+            Some(circle) => frame.with_save(|frame| {
+                frame.translate(Vector::new(center.x, center.y));
+                frame.fill(circle, color);
+            }),
+            None => frame.fill(&Path::circle(center, radius), color),
+        }
+    }
+}
+
+/// Rings stacked to fake a radial falloff for soft-edged flakes
+const SOFT_LAYERS: usize = 4;
+
+/// Draws a flake as shrinking, overlapping translucent circles so it fades out towards the edge
+/// while the centre still reaches the alpha of `color`.
+fn draw_soft_circle(
+    frame: &mut Frame,
+    circles: &CirclePaths,
+    radius: f32,
+    center: Point,
+    color: Color,
+) {
+    let layer_alpha = 1.0 - (1.0 - color.a.clamp(0.0, 0.999)).powf(1.0 / SOFT_LAYERS as f32);
+    let color = Color {
+        a: layer_alpha,
+        ..color
+    };
+
+    for layer in 0..SOFT_LAYERS {
+        let radius = radius * (1.0 - layer as f32 / SOFT_LAYERS as f32);
+        circles.fill(frame, center, radius, color);
+    }
+}
+
+/// Decodes the configured snowflake images up front so broken files fall back to circles
+/// instead of silently rendering nothing. Sprites come back sorted by pixel area, smallest
+/// first, for `image_size_map`.
+fn load_sprites(paths: &[PathBuf]) -> Vec<ImageHandle> {
+    let mut sprites: Vec<(u64, ImageHandle)> = paths
+        .iter()
+        .filter_map(|path| match image::open(path) {
+            Ok(img) => {
+                let rgba = img.into_rgba8();
+                let (width, height) = rgba.dimensions();
+                let handle = ImageHandle::from_rgba(width, height, rgba.into_raw());
+                Some((u64::from(width) * u64::from(height), handle))
+            }
+            Err(e) => {
+                eprintln!("hyprsnow: Failed to load image {}: {}", path.display(), e);
+                None
+            }
+        })
+        .collect();
+
+    sprites.sort_by_key(|(area, _)| *area);
+    sprites.into_iter().map(|(_, handle)| handle).collect()
 }
 
 impl Snowflake {
-    fn new(width: f32, height: f32, config: &SnowConfig, rng: &mut impl Rng) -> Self {
-        let image_index = config
-            .image_paths
-            .as_ref()
-            .and_then(|paths| {
-                if paths.is_empty() { None } else { Some(rng.random_range(0..paths.len())) }
-            });
+    fn new(
+        width: f32,
+        height: f32,
+        config: &SnowConfig,
+        sprite_count: usize,
+        rng: &mut impl Rng,
+    ) -> Self {
+        let radius = random_between(config.size_min, config.size_max, rng);
+        let clump_size = random_clump_size(config, rng);
+        let speed = random_speed(radius, config, rng) * clump_speedup(clump_size);
+        let y = if config.startup_ramp {
+            // Staggered above the top edge so the flakes arrive over the first few seconds
+            -radius - random_between(0.0, speed * STARTUP_RAMP_SECONDS, rng)
+        } else {
+            rng.random_range(0.0..height)
+        };
 
         Self {
             x: rng.random_range(0.0..width),
-            y: rng.random_range(0.0..height),
-            radius: rng.random_range(config.size_min..config.size_max),
-            speed: rng.random_range(config.speed_min..config.speed_max),
+            y,
+            spawn_y: y,
+            radius,
+            speed,
+            vx: 0.0,
+            vy: 0.0,
             phase: rng.random_range(0.0..std::f32::consts::TAU),
-            drift_amount: rng.random_range(0.0..config.drift),
-            opacity: rng.random_range(0.7..1.0) * config.max_opacity,
+            age: 0.0,
+            drift_freq: random_drift_freq(config, rng),
+            drift_amount: random_between(0.0, config.drift, rng),
+            opacity: random_opacity(radius, config, rng),
+            shade: 1.0,
+            retiring: None,
+            rotation: rng.random_range(0.0..std::f32::consts::TAU),
+            spin: random_spin(config, rng),
+            color: random_color(config, rng),
             state: SnowState::Falling,
-            image_index,
+            layer: FlakeLayer::random(config.layer_mode, rng),
+            image_index: pick_image_index(radius, config, sprite_count, rng),
+            home: Rectangle::with_size(Size::new(width, height)),
+            clump_size,
+            shape: random_shape(config, rng),
         }
     }
 
-    fn reset(&mut self, width: f32, height: f32, config: &SnowConfig, rng: &mut impl Rng) {
+    fn reset(
+        &mut self,
+        width: f32,
+        height: f32,
+        config: &SnowConfig,
+        sprite_count: usize,
+        rng: &mut impl Rng,
+    ) {
         self.x = rng.random_range(0.0..width);
         self.y = rng.random_range(-self.radius..height);
-        self.radius = rng.random_range(config.size_min..config.size_max);
-        self.speed = rng.random_range(config.speed_min..config.speed_max);
+        self.spawn_y = self.y;
+        self.radius = random_between(config.size_min, config.size_max, rng);
+        self.clump_size = random_clump_size(config, rng);
+        self.speed = random_speed(self.radius, config, rng) * clump_speedup(self.clump_size);
+        self.vx = 0.0;
+        self.vy = 0.0;
         self.phase = rng.random_range(0.0..std::f32::consts::TAU);
-        self.drift_amount = rng.random_range(0.0..config.drift);
-        self.opacity = rng.random_range(0.7..1.0) * config.max_opacity;
+        self.age = 0.0;
+        self.drift_freq = random_drift_freq(config, rng);
+        self.drift_amount = random_between(0.0, config.drift, rng);
+        self.opacity = random_opacity(self.radius, config, rng);
+        self.shade = 1.0;
+        self.rotation = rng.random_range(0.0..std::f32::consts::TAU);
+        self.spin = random_spin(config, rng);
+        self.color = random_color(config, rng);
         self.state = SnowState::Falling;
+        self.layer = FlakeLayer::random(config.layer_mode, rng);
+        self.image_index = pick_image_index(self.radius, config, sprite_count, rng);
+        self.shape = random_shape(config, rng);
+    }
+}
 
-        self.image_index = config.image_paths.as_ref().and_then(|paths| {
-            if paths.is_empty() { None } else { Some(rng.random_range(0..paths.len())) }
-        });
+/// Horizontal span flakes may spawn in, weighted by the owning monitor's intensity, or by its
+/// area with `density_per_megapixel`
+struct SpawnRange {
+    start: f32,
+    end: f32,
+    /// Top edge of the monitor, or of the overlay if that starts lower, where flakes
+    /// respawning on it appear
+    top: f32,
+    bottom: f32,
+    weight: f32,
+}
+
+/// How strongly a full `spawn_bias` crowds spawns: at ±1 only 1 in 16 flakes spawns in the
+/// far half of a monitor
+const SPAWN_BIAS_STRENGTH: f32 = 3.0;
+
+/// Tries before giving up on finding a spawn point that doesn't immediately land
+const SPAWN_ATTEMPTS: usize = 4;
+/// Free fall a new flake should get above a window top before it may land on it
+const SPAWN_CLEARANCE: f32 = 20.0;
+
+/// Picks where new and respawned flakes appear.
+struct Spawner<'a> {
+    ranges: Vec<SpawnRange>,
+    windows: &'a WindowIndex,
+    /// Column around the pointer, as `(x, half width)`, that new flakes stay out of
+    avoid: Option<(f32, f32)>,
+    /// `spawn_bias`, crowding spawns towards one side of each range
+    bias: f32,
+    width: f32,
+    height: f32,
+}
+
+impl Spawner<'_> {
+    /// A spawn column and the box of the monitor it lies on.
+    fn pick_x(&self, rng: &mut impl Rng) -> (f32, Rectangle) {
+        let everywhere = Rectangle::with_size(Size::new(self.width, self.height));
+        let total: f32 = self.ranges.iter().map(|r| r.weight).sum();
+        if total <= 0.0 {
+            return (self.width * self.skewed(rng), everywhere);
+        }
+
+        let mut pick = rng.random_range(0.0..total);
+        for range in &self.ranges {
+            if pick < range.weight {
+                let x = range.start + (range.end - range.start) * self.skewed(rng);
+                let monitor = Rectangle::new(
+                    Point::new(range.start, range.top),
+                    Size::new(range.end - range.start, range.bottom - range.top),
+                );
+                return (x, monitor);
+            }
+            pick -= range.weight;
+        }
+        (self.width * self.skewed(rng), everywhere)
+    }
+
+    /// A random fraction in 0..1, crowded towards 1 for a positive `bias` and towards 0 for a
+    /// negative one.
+    fn skewed(&self, rng: &mut impl Rng) -> f32 {
+        let exponent = 1.0 / (1.0 + SPAWN_BIAS_STRENGTH * self.bias.abs());
+        let u: f32 = rng.random_range(0.0..1.0);
+        if self.bias >= 0.0 {
+            u.powf(exponent)
+        } else {
+            1.0 - (1.0 - u).powf(exponent)
+        }
+    }
+
+    /// Whether a flake at this spot would land on a window the moment it appears
+    fn lands_immediately(&self, x: f32, y: f32, radius: f32) -> bool {
+        self.windows
+            .at_x(x)
+            .any(|w| y + radius >= w.y - SPAWN_CLEARANCE && y + radius <= w.y)
+    }
+
+    fn is_avoided(&self, x: f32) -> bool {
+        self.avoid
+            .is_some_and(|(center, radius)| (x - center).abs() < radius)
+    }
+
+    /// Moves a (re)spawned flake into a spawn range, retrying spots right on top of windows
+    /// or next to the pointer. Returns the top edge of the monitor it ended up on.
+    fn place(&self, flake: &mut Snowflake, rng: &mut impl Rng) -> f32 {
+        for attempt in 0..SPAWN_ATTEMPTS {
+            if attempt > 0 {
+                flake.y = rng.random_range(-flake.radius..self.height);
+            }
+            (flake.x, flake.home) = self.pick_x(rng);
+
+            if !self.lands_immediately(flake.x, flake.y, flake.radius) && !self.is_avoided(flake.x)
+            {
+                break;
+            }
+        }
+        flake.spawn_y = flake.y;
+        flake.home.y
+    }
+
+    /// Like `place`, but starts the flake just above the top edge of its monitor, which in
+    /// vertical layouts can be far below the top of the overlay.
+    fn place_at_top(&self, flake: &mut Snowflake, rng: &mut impl Rng) {
+        let top = self.place(flake, rng);
+        flake.y = top - flake.radius;
+        flake.spawn_y = flake.y;
     }
 }
 
+/// The snow simulation: flakes, ground piles and wind over an area of the screen, with the
+/// windows and monitors in it as reported by a `WindowSource`.
 pub struct Waysnow {
     snowflakes: Vec<Snowflake>,
+    /// Visible windows, moved from global layout coordinates into the overlay's like the flakes
     windows: Vec<WindowRect>,
+    /// Bars and docks, tracked only while `land_on_bars` is on; in overlay coordinates too
+    bars: Vec<WindowRect>,
+    /// Surfaces snow may land on, i.e. `windows` and `bars` minus excluded ones
+    landing_targets: WindowIndex,
     monitors: Vec<MonitorRect>,
-    event_rx: mpsc::Receiver<crate::hyprland::HyprlandEvent>,
+    focused_window: Option<Address>,
+    /// Windows closed since the last step, to tell them apart from ones that merely left the
+    /// screen for `orphan_behavior`
+    closed_windows: Vec<Address>,
+    /// Monitor holding the focused window, for `active_monitor_only`
+    active_monitor: Option<String>,
+    /// Pointer position in overlay coordinates while `cursor_interaction` or
+    /// `avoid_cursor_radius` needs it
+    cursor: Option<Point>,
+    /// Where flakes recently finished melting, with the seconds left before each mark dries
+    wet_marks: Vec<(Point, f32)>,
+    /// Source of all randomness in the simulation, seeded from `config.seed` when set
+    rng: StdRng,
+    source: Box<dyn WindowSource>,
+    event_rx: mpsc::Receiver<WindowEvent>,
+    /// When `event_rx`'s listener was started, to pace restarts of one that keeps failing
+    listener_started: Instant,
     config_rx: mpsc::Receiver<ConfigEvent>,
     last_tick: Instant,
-    time: f32,
+    wind: Wind,
+    ground: Ground,
     offset_x: f32,
     offset_y: f32,
     width: f32,
     height: f32,
     config: SnowConfig,
+    paused: bool,
+    /// Intensity currently set by `config.schedule`, if it differs from `config.intensity`
+    scheduled_intensity: Option<u8>,
+    last_schedule_check: Instant,
+    /// When shutdown started; no new flakes spawn while the existing ones fade away
+    draining: Option<Instant>,
+    /// Flake count `advance` eases towards, a few flakes per tick
+    target_count: usize,
+    /// Smoothed frames per second, shown by the `debug` overlay
+    frame_rate: f32,
     cache: canvas::Cache,
+    behind_cache: canvas::Cache,
     cached_images: Vec<ImageHandle>,
+    circle_paths: CirclePaths,
 }
 
 impl Waysnow {
-    fn is_in_fullscreen_monitor(&self, x: f32, y: f32) -> bool {
-        for monitor in &self.monitors {
-            let mon_x = monitor.x - self.offset_x;
-            let mon_y = monitor.y - self.offset_y;
-
-            if monitor.has_fullscreen
-                && x >= mon_x
-                && x < mon_x + monitor.width
-                && y < mon_y + monitor.height
-            {
-                return true;
-            }
+    /// Snow covering `bounds`, in the global layout coordinates `source` reports windows and
+    /// monitors in, such as the area of an embedding app's canvas. Flakes start falling right
+    /// away unless `config.startup_ramp` is set.
+    pub fn new(
+        config: SnowConfig,
+        mut bounds: Rectangle,
+        mut source: Box<dyn WindowSource>,
+    ) -> Self {
+        // Odd layouts can report an empty box, and every random position is drawn from it
+        if !(bounds.width >= 1.0 && bounds.height >= 1.0) {
+            eprintln!(
+                "hyprsnow: Screen bounds of {}x{} are unusable, using at least 1x1",
+                bounds.width, bounds.height
+            );
+            bounds.width = bounds.width.max(1.0);
+            bounds.height = bounds.height.max(1.0);
+        }
+        let monitors = source.monitors();
+        let event_rx = source.events();
+        let mut rng = seeded_rng(config.seed);
+        let wind = Wind::new(config.wind_x, config.wind_y, config.gust_strength, &mut rng);
+
+        let mut state = Waysnow {
+            snowflakes: Vec::new(),
+            windows: Vec::new(),
+            bars: Vec::new(),
+            landing_targets: WindowIndex::default(),
+            monitors,
+            focused_window: None,
+            closed_windows: Vec::new(),
+            active_monitor: None,
+            cursor: None,
+            wet_marks: Vec::new(),
+            rng,
+            source,
+            event_rx,
+            listener_started: Instant::now(),
+            // Only the overlay follows the config file; it swaps in a watched channel
+            config_rx: mpsc::channel().1,
+            last_tick: Instant::now(),
+            wind,
+            ground: Ground::new(config.max_pile_height),
+            offset_x: bounds.x,
+            offset_y: bounds.y,
+            width: bounds.width,
+            height: bounds.height,
+            cached_images: load_sprites(&config.image_paths),
+            circle_paths: CirclePaths::new(&config),
+            config,
+            paused: false,
+            scheduled_intensity: None,
+            last_schedule_check: Instant::now(),
+            draining: None,
+            target_count: 0,
+            frame_rate: 0.0,
+            cache: canvas::Cache::default(),
+            behind_cache: canvas::Cache::default(),
+        };
+        state.sync_ground();
+        // Start at the scheduled intensity straight away instead of ramping up to it
+        state.scheduled_intensity = state
+            .config
+            .scheduled_intensity(local_minute_of_day())
+            .filter(|&i| i != state.config.intensity);
+        state.focused_window = state.source.active_window();
+        state.refresh_windows();
+        state.sync_population();
+        state.spawn_flakes(state.target_count);
+
+        state
+    }
+
+    /// A simulation detached from Hyprland, showing on a single `width`x`height` monitor with
+    /// no windows.
+    pub fn offscreen(mut config: SnowConfig, width: f32, height: f32) -> Self {
+        // The only monitor is a made-up one, which an allowlist would hide
+        config.monitors.clear();
+        let bounds = Rectangle::with_size(Size::new(width, height));
+        Self::new(config, bounds, Box::new(NullSource::new(width, height)))
+    }
+
+    /// Runs the physics for `seconds` in steps of one frame at the configured frame rate.
+    pub fn simulate(&mut self, seconds: f32) {
+        let dt = 1.0 / self.config.fps as f32;
+        let mut elapsed = 0.0;
+        while elapsed < seconds {
+            self.advance(dt);
+            elapsed += dt;
+        }
+    }
+
+    /// Whether snow at this overlay position must not be shown, because its monitor is showing
+    /// a fullscreen window, isn't one of the configured `monitors` or, with `active_monitor_only`,
+    /// isn't the focused one
+    fn is_hidden_at(&self, x: f32, y: f32) -> bool {
+        let monitor = self.monitors.iter().find(|m| {
+            let mon_x = m.x - self.offset_x;
+            let mon_y = m.y - self.offset_y;
+            x >= mon_x && x < mon_x + m.width && y >= mon_y && y < mon_y + m.height
+        });
+
+        match monitor {
+            Some(m) => !self.shows_monitor(&m.name) || self.is_suppressed(m),
+            // Gaps between monitors of different sizes are never seen anyway
+            None => !self.config.monitors.is_empty() || self.config.active_monitor_only,
         }
-        false
     }
 
-    fn get_valid_spawn_ranges(&self) -> Vec<(f32, f32)> {
+    /// Whether `hide_on_fullscreen` keeps snow off this monitor, going by the window states in
+    /// `suppress_on`
+    fn is_suppressed(&self, monitor: &MonitorRect) -> bool {
+        let suppress_on = &self.config.suppress_on;
+        self.config.hide_on_fullscreen
+            && ((monitor.has_fullscreen && suppress_on.contains(&SuppressOn::Fullscreen))
+                || (monitor.has_maximized && suppress_on.contains(&SuppressOn::Maximized)))
+    }
+
+    /// Monitors new snow may start on: shown, and not suppressed by `hide_on_fullscreen`
+    fn spawn_monitors(&self) -> impl Iterator<Item = &MonitorRect> {
         self.monitors
             .iter()
-            .filter(|m| !m.has_fullscreen)
+            .filter(|m| !self.is_suppressed(m) && self.shows_monitor(&m.name))
+    }
+
+    fn get_valid_spawn_ranges(&self) -> Vec<SpawnRange> {
+        self.spawn_monitors()
             .map(|m| {
                 let mon_x = m.x - self.offset_x;
-                (mon_x, mon_x + m.width)
+                SpawnRange {
+                    start: mon_x,
+                    end: mon_x + m.width,
+                    top: (m.y - self.offset_y).max(0.0),
+                    bottom: (m.y - self.offset_y + m.height).min(self.height),
+                    weight: match self.config.density_per_megapixel {
+                        Some(_) => m.width * m.height,
+                        None => self.monitor_intensity(m) as f32 * m.width * m.height,
+                    },
+                }
             })
             .collect()
     }
 
-    fn apply_config_change(&mut self, new_config: SnowConfig) {
-        let mut rng = rand::rng();
-        let old_count = self.config.intensity as usize * 50;
-        let new_count = new_config.intensity as usize * 50;
-
-        if self.config.image_paths != new_config.image_paths {
-            self.cached_images.clear();
-            if let Some(paths) = &new_config.image_paths {
-                for p in paths {
-                    self.cached_images.push(ImageHandle::from_path(p));
-                }
-            }
-            self.cache.clear();
-        }
+    /// Intensity in effect: the schedule's while it has a say, otherwise the configured one
+    fn intensity(&self) -> u8 {
+        self.scheduled_intensity.unwrap_or(self.config.intensity)
+    }
 
-        self.config = new_config;
+    /// Intensity of a `monitor[...]` block, or the one in effect for monitors without one
+    fn monitor_intensity(&self, monitor: &MonitorRect) -> u8 {
+        self.config
+            .intensity_for_monitor(&monitor.name, self.intensity())
+    }
 
-        if new_count > old_count {
-            let valid_x_ranges = self.get_valid_spawn_ranges();
-            for _ in old_count..new_count {
-                let mut flake = Snowflake::new(self.width, self.height, &self.config, &mut rng);
-                if !valid_x_ranges.is_empty() {
-                    let range = &valid_x_ranges[rng.random_range(0..valid_x_ranges.len())];
-                    flake.x = rng.random_range(range.0..range.1);
-                }
-                self.snowflakes.push(flake);
-            }
-        } else if new_count < old_count {
-            self.snowflakes.truncate(new_count);
+    /// Steps the intensity one level towards what the schedule asks for right now, so the
+    /// flake count changes gradually rather than all at once.
+    fn follow_schedule(&mut self) {
+        let goal = self
+            .config
+            .scheduled_intensity(local_minute_of_day())
+            .unwrap_or(self.config.intensity);
+        let current = self.intensity();
+        let next = match current.cmp(&goal) {
+            Ordering::Less => current + 1,
+            Ordering::Greater => current - 1,
+            Ordering::Equal => current,
+        };
+
+        let scheduled = (next != self.config.intensity).then_some(next);
+        if scheduled != self.scheduled_intensity {
+            self.scheduled_intensity = scheduled;
+            self.sync_population();
         }
     }
-}
 
-#[to_layer_message]
-#[derive(Debug, Clone)]
-pub enum Message {
-    Tick(Instant),
-}
+    /// Number of flakes to simulate, summing per-monitor intensities when any are configured.
+    fn wanted_count(&self) -> usize {
+        let area: f32 = self.spawn_monitors().map(|m| m.width * m.height).sum();
+        if let (None, Some(density)) = (self.config.count, self.config.density_per_megapixel) {
+            return (area / 1_000_000.0 * density).round() as usize;
+        }
+        if self.config.monitor_intensity.is_empty() || self.config.count.is_some() || area <= 0.0 {
+            return self.config.flake_count(self.intensity());
+        }
 
-/// Boot function - initializes the application state
-pub fn boot(config: SnowConfig) -> (Waysnow, Task<Message>) {
-    let mut rng = rand::rng();
-    let (min_x, min_y, max_x, max_y) = get_total_screen_bounds();
-    let width = max_x - min_x;
-    let height = max_y - min_y;
-    let count = config.intensity as usize * 50;
+        // Each monitor contributes its intensity's count in proportion to its share of the area
+        let count: f32 = self
+            .spawn_monitors()
+            .map(|m| {
+                let count = self.config.flake_count(self.monitor_intensity(m));
+                count as f32 * m.width * m.height / area
+            })
+            .sum();
+        count.round() as usize
+    }
 
-    let snowflakes = (0..count)
-        .map(|_| Snowflake::new(width, height, &config, &mut rng))
-        .collect();
+    fn refresh_windows(&mut self) {
+        self.windows = self.to_overlay(self.source.windows());
+        self.bars = if self.config.land_on_bars {
+            self.to_overlay(self.source.layer_surfaces())
+        } else {
+            Vec::new()
+        };
+        self.refresh_landing_targets();
+        self.track_active_monitor();
+    }
 
-    let windows = get_hyprland_windows();
-    let monitors = get_monitors_with_fullscreen_state();
-    let event_rx = spawn_event_listener();
-    let config_rx = spawn_config_watcher();
+    /// Moves surfaces reported in global layout coordinates into the overlay's, so they can be
+    /// compared with flakes directly even when the overlay doesn't start at the origin.
+    fn to_overlay(&self, mut surfaces: Vec<WindowRect>) -> Vec<WindowRect> {
+        for surface in &mut surfaces {
+            surface.x -= self.offset_x;
+            surface.y -= self.offset_y;
+        }
+        surfaces
+    }
+
+    /// Follows focus to the monitor holding the focused window. Without a focused window the
+    /// snow stays where it was.
+    fn track_active_monitor(&mut self) {
+        let Some(window) = self
+            .windows
+            .iter()
+            .find(|w| Some(&w.address) == self.focused_window.as_ref())
+        else {
+            return;
+        };
 
-    let mut cached_images = Vec::new();
-    if let Some(paths) = &config.image_paths {
-        for p in paths {
-            cached_images.push(ImageHandle::from_path(p));
+        let x = window.x + self.offset_x + window.width / 2.0;
+        let y = window.y + self.offset_y + window.height / 2.0;
+        if let Some(monitor) = self
+            .monitors
+            .iter()
+            .find(|m| x >= m.x && x < m.x + m.width && y >= m.y && y < m.y + m.height)
+            && self.active_monitor.as_ref() != Some(&monitor.name)
+        {
+            self.active_monitor = Some(monitor.name.clone());
+            // With `active_monitor_only` the snow moves to a monitor of a different size
+            self.sync_population();
         }
     }
 
-    (
-        Waysnow {
-            snowflakes,
-            windows,
-            monitors,
-            event_rx,
-            config_rx,
-            last_tick: Instant::now(),
-            time: 0.0,
-            offset_x: min_x,
-            offset_y: min_y,
-            width,
-            height,
-            config,
-            cache: canvas::Cache::default(),
-            cached_images,
-        },
-        Task::none(),
-    )
-}
+    /// Whether snow may show on the monitor with this connector name, by `monitors` and
+    /// `active_monitor_only`
+    fn shows_monitor(&self, name: &str) -> bool {
+        let focused_elsewhere = self
+            .active_monitor
+            .as_ref()
+            .is_some_and(|active| active != name);
+        self.config.is_monitor_enabled(name)
+            && !(self.config.active_monitor_only && focused_elsewhere)
+    }
 
-/// Update function - handles messages and updates state
-pub fn update(state: &mut Waysnow, message: Message) -> Task<Message> {
-    match message {
-        Message::Tick(now) => {
-            let dt = now.duration_since(state.last_tick).as_secs_f32();
-            state.last_tick = now;
-            state.time += dt;
-
-            // Check for hyprland events (non-blocking)
-            while let Ok(_event) = state.event_rx.try_recv() {
-                state.windows = get_hyprland_windows();
-                state.monitors = get_monitors_with_fullscreen_state();
-            }
+    /// Windows and, with `land_on_bars`, bars: everything snow can rest on or hide behind
+    fn surfaces(&self) -> impl Iterator<Item = &WindowRect> {
+        self.windows.iter().chain(&self.bars)
+    }
 
-            // Check for config changes (non-blocking)
-            while let Ok(ConfigEvent::ConfigChanged(new_config)) = state.config_rx.try_recv() {
-                state.apply_config_change(new_config);
-            }
+    fn refresh_monitors(&mut self) {
+        self.monitors = self.source.monitors();
+        self.track_active_monitor();
+        self.sync_ground();
+        self.sync_population();
+    }
 
-            let mut rng = rand::rng();
-            let melt_duration = 4.0;
-            let valid_x_ranges = state.get_valid_spawn_ranges();
+    fn sync_ground(&mut self) {
+        let edges = self
+            .monitors
+            .iter()
+            .filter(|m| self.config.is_monitor_enabled(&m.name))
+            .map(|m| GroundEdge {
+                name: m.name.clone(),
+                left: m.x - self.offset_x,
+                width: m.width,
+                // A `region` band can end above the monitor's bottom edge
+                bottom: (m.y - self.offset_y + m.height).min(self.height),
+            })
+            .collect();
+        self.ground.sync(edges);
+    }
 
-            for flake in &mut state.snowflakes {
-                match &mut flake.state {
-                    SnowState::Falling => {
-                        flake.y += flake.speed * dt;
-                        flake.x += (state.time + flake.phase).sin() * flake.drift_amount * dt;
+    fn refresh_landing_targets(&mut self) {
+        // A top edge above the viewport can't be seen, so snow resting on it would appear to
+        // float; let it fall past such windows instead. `land_on` only picks among windows, bars
+        // are up to `land_on_bars`.
+        let windows = self
+            .windows
+            .iter()
+            .filter(|w| self.config.lands_on(w.floating));
+        self.landing_targets = WindowIndex::new(
+            windows
+                .chain(&self.bars)
+                .filter(|w| w.y >= 0.0 && !self.config.is_excluded(&w.class, &w.title))
+                .cloned()
+                .collect(),
+        );
+    }
 
-                        if flake.x < 0.0 {
-                            flake.x = state.width;
-                        } else if flake.x > state.width {
-                            flake.x = 0.0;
-                        }
+    /// Updates `target_count` for the current config and monitors. Outside of draining, which
+    /// drops flakes at once, the count only gets there gradually through `ease_population`.
+    fn sync_population(&mut self) {
+        self.target_count = self.wanted_count();
+        if self.draining.is_some() {
+            self.snowflakes.truncate(self.target_count);
+        }
+    }
 
-                        let flake_bottom = flake.y + flake.radius;
-                        let mut landed = false;
+    fn spawn_flakes(&mut self, count: usize) {
+        let spawner = Spawner {
+            ranges: self.get_valid_spawn_ranges(),
+            windows: &self.landing_targets,
+            avoid: self.avoided_column(),
+            bias: self.config.spawn_bias,
+            width: self.width,
+            height: self.height,
+        };
+        let rng = &mut self.rng;
+        let new_flakes: Vec<Snowflake> = (0..count)
+            .map(|_| {
+                let mut flake = Snowflake::new(
+                    self.width,
+                    self.height,
+                    &self.config,
+                    self.cached_images.len(),
+                    rng,
+                );
+                spawner.place(&mut flake, rng);
+                flake
+            })
+            .collect();
+        self.snowflakes.extend(new_flakes);
+    }
 
-                        for window in &state.windows {
-                            if flake.x >= window.x
-                                && flake.x <= window.x + window.width
-                                && flake_bottom >= window.y
-                                && flake.y < window.y + 10.0
-                            {
-                                flake.y = window.y - flake.radius;
-                                flake.state = SnowState::Landed {
-                                    melt_timer: 0.0,
-                                    window_addr: Some(window.address.clone()),
-                                    offset_x: flake.x - window.x,
-                                };
-                                landed = true;
-                                break;
-                            }
-                        }
+    /// Moves the flake count a step towards `target_count`, closing a share of the gap each
+    /// tick so big changes start fast and settle softly. Surplus flakes fade out before they go.
+    fn ease_population(&mut self, dt: f32) {
+        let live = self
+            .snowflakes
+            .iter()
+            .filter(|f| f.retiring.is_none())
+            .count();
+        let share = (dt / POPULATION_EASE_SECONDS).min(1.0);
+        let step = |gap: usize| (gap as f32 * share).ceil() as usize;
+        match live.cmp(&self.target_count) {
+            Ordering::Less => self.spawn_flakes(step(self.target_count - live)),
+            Ordering::Greater => {
+                let surplus = step(live - self.target_count);
+                self.snowflakes
+                    .iter_mut()
+                    .rev()
+                    .filter(|f| f.retiring.is_none())
+                    .take(surplus)
+                    .for_each(|f| f.retiring = Some(1.0));
+            }
+            Ordering::Equal => {}
+        }
+    }
 
-                        if !landed && flake.y > state.height - flake.radius {
-                            flake.y = state.height - flake.radius;
-                            flake.state = SnowState::Landed {
-                                melt_timer: 0.0,
-                                window_addr: None,
-                                offset_x: 0.0,
-                            };
-                        }
-                    }
-                    SnowState::Landed {
-                        melt_timer,
-                        window_addr,
-                        offset_x,
-                    } => {
-                        if let Some(addr) = window_addr {
-                            if let Some(window) =
-                                state.windows.iter().find(|w| &w.address == addr)
-                            {
-                                let expected_y = window.y - flake.radius;
+    /// Whether the simulation is parked because no snow could be seen anyway: no windows are
+    /// visible, fullscreen apps cover every monitor snow is shown on, or none of those monitors
+    /// is connected
+    fn is_idle(&self) -> bool {
+        let mut shown = self
+            .monitors
+            .iter()
+            .filter(|m| self.shows_monitor(&m.name))
+            .peekable();
+        // None of the configured `monitors` is connected
+        let none_shown = shown.peek().is_none() && !self.config.monitors.is_empty();
+        let all_fullscreen = shown.peek().is_some() && shown.all(|m| self.is_suppressed(m));
+        none_shown || all_fullscreen || (self.config.idle_when_empty && self.windows.is_empty())
+    }
 
-                                if (flake.y - expected_y).abs() > 1.0
-                                    || *offset_x < 0.0
-                                    || *offset_x > window.width
-                                {
-                                    flake.state = SnowState::Falling;
-                                    continue;
-                                }
+    /// Opacity factor fading everything out while draining
+    fn drain_fade(&self) -> f32 {
+        self.draining.map_or(1.0, |start| {
+            let elapsed = self.last_tick.saturating_duration_since(start);
+            (1.0 - elapsed.as_secs_f32() / DRAIN_TIME.as_secs_f32()).max(0.0)
+        })
+    }
 
-                                flake.x = window.x + *offset_x;
-                            } else {
-                                flake.state = SnowState::Falling;
+    /// Column around the pointer that new flakes keep out of, with `avoid_cursor_radius`
+    fn avoided_column(&self) -> Option<(f32, f32)> {
+        let radius = self.config.avoid_cursor_radius;
+        self.cursor
+            .filter(|_| radius > 0.0)
+            .map(|cursor| (cursor.x, radius))
+    }
+
+    /// Numbers shown by the `debug` overlay
+    fn debug_text(&self) -> String {
+        let mut lines = vec![
+            format!("{:.0} fps", self.frame_rate),
+            format!("{} flakes", self.snowflakes.len()),
+            format!(
+                "{} windows, {} bars, {} monitors",
+                self.windows.len(),
+                self.bars.len(),
+                self.monitors.len()
+            ),
+        ];
+        if self.paused {
+            lines.push(String::from("paused"));
+        } else if self.is_idle() {
+            lines.push(String::from("idle"));
+        }
+        lines.join("\n")
+    }
+
+    /// Whether a shutdown has run its course and the app can exit
+    fn is_drained(&self) -> bool {
+        self.draining.is_some_and(|start| {
+            self.snowflakes.is_empty()
+                || self.last_tick.saturating_duration_since(start) >= DRAIN_TIME
+        })
+    }
+
+    /// Alpha a flake is drawn with, after fading in, dimming behind windows, retiring,
+    /// twinkling and the shutdown fade
+    fn drawn_opacity(&self, flake: &Snowflake, drain_fade: f32) -> f32 {
+        let fade_in = if self.config.fade_in_distance > 0.0 {
+            ((flake.y - flake.spawn_y).abs() / self.config.fade_in_distance).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        let retiring = flake.retiring.unwrap_or(1.0);
+        let opacity = flake.opacity * fade_in * flake.shade * retiring * drain_fade;
+        if self.config.twinkle && matches!(flake.state, SnowState::Falling) {
+            opacity * twinkle_factor(flake)
+        } else {
+            opacity
+        }
+    }
+
+    fn draw_flakes(&self, frame: &mut Frame, layer: FlakeLayer) {
+        if self.is_idle() {
+            return;
+        }
+
+        let bounds = frame.size();
+        let drain_fade = self.drain_fade();
+        let round_snow = self.config.mode == ParticleMode::Snow && !self.config.soft_edges;
+        let mut blanket = Vec::new();
+        // Snow resting on a window takes the color `class_colors` gives the window's class
+        let tints: Vec<(&Address, Color)> = self
+            .surfaces()
+            .filter_map(|w| Some((&w.address, self.config.class_color(&w.class)?)))
+            .collect();
+        for flake in self.snowflakes.iter().filter(|f| f.layer == layer) {
+            if !is_on_canvas(flake, bounds) || self.is_hidden_at(flake.x, flake.y) {
+                continue;
+            }
+
+            let landed_on = match &flake.state {
+                SnowState::Landed { window_addr, .. } => Some(window_addr),
+                SnowState::Falling => None,
+            };
+            // Snow on the ground already shows as part of the pile it was deposited into
+            if landed_on == Some(&None)
+                && self.config.max_pile_height > 0.0
+                && self.config.mode == ParticleMode::Snow
+                && flake.image_index.is_none()
+            {
+                continue;
+            }
+
+            let opacity = self.drawn_opacity(flake, drain_fade);
+            if opacity < MIN_VISIBLE_OPACITY {
+                continue;
+            }
+            let tint = match landed_on {
+                Some(Some(address)) => tints.iter().find(|(a, _)| *a == address),
+                _ => None,
+            };
+            let color = Color {
+                a: opacity,
+                ..tint.map_or(flake.color, |&(_, color)| color)
+            };
+
+            let image = flake
+                .image_index
+                .and_then(|idx| self.cached_images.get(idx));
+            if image.is_none()
+                && round_snow
+                && landed_on.is_some()
+                && flake.shape == FlakeShape::Circle
+            {
+                blanket.extend(flake_centers(flake).map(|center| (color, center, flake.radius)));
+                continue;
+            }
+
+            // The overlay is see-through, so old frames can't be faded out under a dark quad;
+            // copies of the flake a little further back along its path stand in for them
+            if self.config.trail_strength > 0.0 && landed_on.is_none() {
+                for step in (1..=TRAIL_COPIES).rev() {
+                    let back = step as f32 * TRAIL_SPACING;
+                    let fade = 1.0 - step as f32 / (TRAIL_COPIES + 1) as f32;
+                    frame.with_save(|frame| {
+                        frame.translate(Vector::new(-flake.vx * back, -flake.vy * back));
+                        let color = Color {
+                            a: opacity * self.config.trail_strength * fade,
+                            ..color
+                        };
+                        self.draw_flake(frame, flake, image, color);
+                    });
+                }
+            }
+            self.draw_flake(frame, flake, image, color);
+        }
+
+        draw_blanket(frame, blanket);
+    }
+
+    /// Paints one flake as its image if it has one, at the opacity in `color`, or else as its
+    /// shape filled with `color`.
+    fn draw_flake(
+        &self,
+        frame: &mut Frame,
+        flake: &Snowflake,
+        image: Option<&ImageHandle>,
+        color: Color,
+    ) {
+        if let Some(handle) = image {
+            let size = flake.radius * 2.0;
+            frame.draw_image(
+                Rectangle {
+                    x: flake.x - flake.radius,
+                    y: flake.y - flake.radius,
+                    width: size,
+                    height: size,
+                },
+                Image::new(handle.clone())
+                    .opacity(color.a)
+                    .rotation(Radians(flake.rotation)),
+            );
+            return;
+        }
+
+        if self.config.soft_edges
+            && self.config.mode == ParticleMode::Snow
+            && flake.shape == FlakeShape::Circle
+        {
+            for center in flake_centers(flake) {
+                draw_soft_circle(frame, &self.circle_paths, flake.radius, center, color);
+            }
+            return;
+        }
+
+        // Clumps stay one path, so the overlaps between their flakes aren't filled twice
+        if self.config.mode == ParticleMode::Snow
+            && flake.shape == FlakeShape::Circle
+            && flake.clump_size == 1
+        {
+            let center = Point::new(flake.x, flake.y);
+            self.circle_paths.fill(frame, center, flake.radius, color);
+            return;
+        }
+
+        let shape = match self.config.mode {
+            ParticleMode::Snow => Path::new(|builder| {
+                for center in flake_centers(flake) {
+                    match flake.shape {
+                        FlakeShape::Circle => builder.circle(center, flake.radius),
+                        shape => add_crystal(builder, shape, center, flake.radius, flake.rotation),
+                    }
+                }
+            }),
+            ParticleMode::Rain => raindrop_path(flake),
+            ParticleMode::Leaves => leaf_path(flake),
+        };
+        frame.fill(&shape, color);
+    }
+
+    /// Fades `tint_color` in towards the edges of every monitor snow is shown on.
+    fn draw_tint(&self, frame: &mut Frame) {
+        if self.config.tint_strength <= 0.0 {
+            return;
+        }
+
+        let edge = Color {
+            a: self.config.tint_strength * TINT_MAX_OPACITY * self.drain_fade(),
+            ..self.config.tint_color
+        };
+        let clear = Color { a: 0.0, ..edge };
+
+        for m in &self.monitors {
+            let (x, y) = (m.x - self.offset_x, m.y - self.offset_y);
+            if self.is_hidden_at(x + m.width / 2.0, y + m.height / 2.0) {
+                continue;
+            }
+
+            let depth = m.width.min(m.height) * TINT_DEPTH;
+            let mut band = |from: Point, to: Point, size: Size| {
+                let fill = gradient::Linear::new(from, to)
+                    .add_stop(0.0, edge)
+                    .add_stop(1.0, clear);
+                let top_left = Point::new(from.x.min(to.x), from.y.min(to.y));
+                frame.fill_rectangle(top_left, size, fill);
+            };
+
+            // Each band fades from the monitor edge, where the tint is strongest, inwards
+            let (right, bottom) = (x + m.width, y + m.height);
+            let rows = Size::new(m.width, depth);
+            let columns = Size::new(depth, m.height);
+            band(Point::new(x, y), Point::new(x, y + depth), rows);
+            band(Point::new(x, bottom), Point::new(x, bottom - depth), rows);
+            band(Point::new(x, y), Point::new(x + depth, y), columns);
+            band(Point::new(right, y), Point::new(right - depth, y), columns);
+        }
+    }
+
+    fn draw_wet_marks(&self, frame: &mut Frame) {
+        for (point, remaining) in &self.wet_marks {
+            if self.is_hidden_at(point.x, point.y) {
+                continue;
+            }
+
+            let color = Color {
+                a: WET_MARK_OPACITY * remaining / WET_MARK_SECONDS * self.drain_fade(),
+                ..Color::BLACK
+            };
+            self.circle_paths
+                .fill(frame, *point, WET_MARK_RADIUS, color);
+        }
+    }
+
+    fn apply_config_change(&mut self, new_config: SnowConfig) {
+        if new_config.seed != self.config.seed {
+            self.rng = seeded_rng(new_config.seed);
+        }
+        let rng = &mut self.rng;
+
+        if self.config.image_paths != new_config.image_paths
+            || self.config.image_size_map != new_config.image_size_map
+        {
+            if self.config.image_paths != new_config.image_paths {
+                self.cached_images = load_sprites(&new_config.image_paths);
+            }
+            let sprite_count = self.cached_images.len();
+            for flake in &mut self.snowflakes {
+                flake.image_index =
+                    pick_image_index(flake.radius, &new_config, sprite_count, rng);
+            }
+            self.cache.clear();
+        }
+        if self.config.size_max != new_config.size_max {
+            self.circle_paths = CirclePaths::new(&new_config);
+        }
+
+        if self.config.color != new_config.color || self.config.palette != new_config.palette {
+            for flake in &mut self.snowflakes {
+                flake.color = random_color(&new_config, rng);
+            }
+        }
+
+        // Landed flakes pick the new ceiling up on their next melt step by themselves
+        if self.config.max_opacity != new_config.max_opacity
+            || self.config.parallax != new_config.parallax
+        {
+            for flake in &mut self.snowflakes {
+                if matches!(flake.state, SnowState::Falling) {
+                    flake.opacity = random_opacity(flake.radius, &new_config, rng);
+                }
+            }
+        }
+
+        if self.config.shapes != new_config.shapes {
+            for flake in &mut self.snowflakes {
+                flake.shape = random_shape(&new_config, rng);
+            }
+        }
+
+        if self.config.layer_mode != new_config.layer_mode {
+            for flake in &mut self.snowflakes {
+                flake.layer = FlakeLayer::random(new_config.layer_mode, rng);
+            }
+        }
+
+        self.wind.set_strength(
+            new_config.wind_x,
+            new_config.wind_y,
+            new_config.gust_strength,
+        );
+        self.ground.set_max_height(new_config.max_pile_height);
+        let bars_toggled = self.config.land_on_bars != new_config.land_on_bars;
+        self.config = new_config;
+        self.sync_ground();
+        if bars_toggled {
+            self.refresh_windows();
+        } else {
+            self.refresh_landing_targets();
+        }
+        self.sync_population();
+    }
+
+    /// Takes in the window events received since the last call, refetching whatever they made
+    /// stale, restarts the source's listener if it died and fetches the pointer position. This
+    /// is the only place the simulation talks to its source, so callers decide when that happens.
+    pub fn poll_source(&mut self) {
+        let now = Instant::now();
+        // Drain every pending event first (non-blocking) and refetch once afterwards, so a
+        // burst of events, e.g. while dragging a window, costs a single round of IPC calls
+        let mut windows_stale = false;
+        let mut monitors_stale = false;
+        loop {
+            let event = match self.event_rx.try_recv() {
+                Ok(event) => event,
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    // The listener is gone, most likely after a panic. Start a new one, but not
+                    // on every frame if it keeps dying straight away
+                    if now.duration_since(self.listener_started) >= LISTENER_RESTART_DELAY {
+                        eprintln!("hyprsnow: Window event listener stopped, restarting it");
+                        self.event_rx = self.source.events();
+                        self.listener_started = now;
+                        // Whatever happened in the meantime was missed
+                        windows_stale = true;
+                        monitors_stale = true;
+                    }
+                    break;
+                }
+            };
+            match event {
+                WindowEvent::WindowOpened | WindowEvent::WindowMoved => windows_stale = true,
+                WindowEvent::WindowClosed(address) => {
+                    self.closed_windows.push(address);
+                    windows_stale = true;
+                }
+                WindowEvent::ActiveWindowChanged(address) => {
+                    self.focused_window = address;
+                    windows_stale = true;
+                }
+                WindowEvent::FullscreenChanged => monitors_stale = true,
+                WindowEvent::WorkspaceChanged
+                | WindowEvent::SpecialWorkspaceChanged
+                | WindowEvent::Reconnected => {
+                    windows_stale = true;
+                    monitors_stale = true;
+                }
+                WindowEvent::Disconnected => {
+                    // Nothing known about windows can be trusted until Hyprland is back, and
+                    // asking it before then is pointless
+                    self.windows.clear();
+                    self.bars.clear();
+                    self.refresh_landing_targets();
+                    windows_stale = false;
+                    monitors_stale = false;
+                }
+            }
+        }
+        if windows_stale {
+            self.refresh_windows();
+        }
+        if monitors_stale {
+            self.refresh_monitors();
+        }
+
+        let wants_cursor = self.config.cursor_interaction || self.config.avoid_cursor_radius > 0.0;
+        self.cursor = if wants_cursor && !self.is_idle() {
+            self.source
+                .cursor_position()
+                .map(|(x, y)| Point::new(x - self.offset_x, y - self.offset_y))
+        } else {
+            None
+        };
+    }
+
+    /// Advances the snow by `dt` seconds with the layout and pointer last fetched by
+    /// `poll_source`, without talking to the source. Nothing moves while no monitor is showing
+    /// snow.
+    pub fn step(&mut self, dt: f32) {
+        if self.is_idle() {
+            return;
+        }
+        self.advance(dt);
+    }
+
+    /// Paints everything, back to front, onto a frame the size of the snow's bounds.
+    pub fn draw_into(&self, frame: &mut Frame) {
+        self.draw_behind(frame);
+        self.draw_flakes(frame, FlakeLayer::Front);
+    }
+
+    /// The tint, ground piles, wet marks and behind-layer flakes, which front flakes cover
+    fn draw_behind(&self, frame: &mut Frame) {
+        self.draw_tint(frame);
+
+        let pile_color = Color {
+            a: self.config.max_opacity * self.drain_fade(),
+            ..self.config.color
+        };
+        let pile_paths = self.ground.paths(|x, y| !self.is_hidden_at(x, y));
+        for path in &pile_paths {
+            frame.fill(path, pile_color);
+        }
+
+        self.draw_wet_marks(frame);
+        self.draw_flakes(frame, FlakeLayer::Behind);
+    }
+
+    /// Advances the physics by `dt` seconds using only the state already fetched, without
+    /// talking to Hyprland. Random choices come from `self.rng`, so a seeded run is repeatable.
+    fn advance(&mut self, dt: f32) {
+        if self.draining.is_none() {
+            self.ease_population(dt);
+        }
+        let gravity = self.config.gravity;
+        let cursor = self.cursor.filter(|_| self.config.cursor_interaction);
+        let spawner = Spawner {
+            ranges: self.get_valid_spawn_ranges(),
+            windows: &self.landing_targets,
+            avoid: self.avoided_column(),
+            bias: self.config.spawn_bias,
+            width: self.width,
+            height: self.height,
+        };
+        let rng = &mut self.rng;
+        self.wind.update(dt, rng);
+        let (wind_x, wind_y) = self.wind.speed();
+        self.ground.blow(wind_x, dt);
+        let sprite_count = self.cached_images.len();
+        // While draining, flakes that would respawn are dropped instead, as are retiring ones
+        let draining = self.draining.is_some();
+        let mut spent = Vec::new();
+
+        self.wet_marks.retain_mut(|(_, remaining)| {
+            *remaining -= dt;
+            *remaining > 0.0
+        });
+
+        for (i, flake) in self.snowflakes.iter_mut().enumerate() {
+            if let Some(left) = &mut flake.retiring {
+                *left -= dt / RETIRE_SECONDS;
+                if *left <= 0.0 {
+                    spent.push(i);
+                    continue;
+                }
+            }
+            match &mut flake.state {
+                SnowState::Falling => {
+                    flake.age += dt;
+                    let max_lifetime = self.config.max_lifetime;
+                    if max_lifetime > 0.0 && flake.age > max_lifetime {
+                        if draining || flake.retiring.is_some() {
+                            spent.push(i);
+                            continue;
+                        }
+                        flake.reset(self.width, self.height, &self.config, sprite_count, rng);
+                        spawner.place_at_top(flake, rng);
+                        continue;
+                    }
+
+                    let prev_y = flake.y;
+                    let prev_bottom = flake.y + flake.radius;
+                    flake.vy = if gravity > 0.0 {
+                        (flake.vy + gravity * dt).min(terminal_velocity(flake.radius, &self.config))
+                    } else {
+                        flake.speed
+                    };
+                    // Wrapped so the phase never grows large enough to lose precision on long runs
+                    flake.phase = (flake.phase + flake.drift_freq * dt) % std::f32::consts::TAU;
+                    flake.vx = flake.phase.sin() * flake.drift_amount + wind_x;
+                    // Behind-layer flakes dim, and with `behind_slowdown` lag, while a window
+                    // covers them, easing in and out so they seem to pass behind the glass
+                    let covered = flake.layer == FlakeLayer::Behind
+                        && self.windows.iter().chain(&self.bars).any(|w| {
+                            flake.x >= w.x
+                                && flake.x <= w.x + w.width
+                                && flake.y >= w.y
+                                && flake.y <= w.y + w.height
+                        });
+                    let shade = if covered { BEHIND_DIM } else { 1.0 };
+                    flake.shade += (shade - flake.shade) * (dt / BEHIND_FADE_SECONDS).min(1.0);
+                    let drag = 1.0
+                        - self.config.behind_slowdown * (1.0 - flake.shade) / (1.0 - BEHIND_DIM);
+                    let settle = if self.config.land {
+                        self.ground
+                            .surface_at(flake.x)
+                            .map_or(1.0, |surface| settle_factor(surface - prev_bottom))
+                    } else {
+                        1.0
+                    };
+                    flake.x += flake.vx * dt;
+                    flake.y += (flake.vy + wind_y * buoyancy(flake.radius, &self.config))
+                        * settle
+                        * drag
+                        * dt;
+                    if let Some(cursor) = cursor {
+                        let (push_x, push_y) = cursor_push(flake.x, flake.y, cursor);
+                        flake.x += push_x * dt;
+                        flake.y += push_y * dt;
+                    }
+                    // A negative speed makes a flake rise on its own; once gone over the top it
+                    // won't come back, so it starts over like a melted one
+                    let rising = flake.speed < 0.0 && gravity <= 0.0;
+                    if rising && flake.y + flake.radius < 0.0 {
+                        if draining || flake.retiring.is_some() {
+                            spent.push(i);
+                            continue;
+                        }
+                        flake.reset(self.width, self.height, &self.config, sprite_count, rng);
+                        spawner.place(flake, rng);
+                        continue;
+                    }
+                    // Flakes lofted out of view wait at the top edge until the updraft eases
+                    if flake.y < prev_y && !rising {
+                        flake.y = flake.y.max((-flake.radius).min(prev_y));
+                    }
+                    flake.rotation = (flake.rotation + flake.spin * dt) % std::f32::consts::TAU;
+
+                    // Carry the overshoot across so drift continues smoothly past the edge
+                    let area = if self.config.per_monitor_wrap {
+                        flake.home
+                    } else {
+                        Rectangle::with_size(Size::new(self.width, self.height))
+                    };
+                    let right = area.x + area.width;
+                    if flake.x < area.x || flake.x >= right {
+                        flake.x = area.x + (flake.x - area.x).rem_euclid(area.width);
+                    }
+
+                    if !self.config.land {
+                        if flake.y - flake.radius > area.y + area.height {
+                            if draining || flake.retiring.is_some() {
+                                spent.push(i);
                                 continue;
                             }
+                            flake.reset(self.width, self.height, &self.config, sprite_count, rng);
+                            spawner.place_at_top(flake, rng);
                         }
+                        continue;
+                    }
+
+                    let flake_bottom = flake.y + flake.radius;
 
-                        *melt_timer += dt;
-                        let melt_progress = *melt_timer / melt_duration;
-                        flake.opacity = (1.0 - melt_progress).max(0.0) * 0.9 * state.config.max_opacity;
+                    // Land on any top edge crossed during this step, however far the flake moved,
+                    // so fast flakes and low frame rates can't tunnel through
+                    let margin = self.config.land_margin;
+                    let landing = match flake.layer {
+                        FlakeLayer::Front => self.landing_targets.at_x(flake.x).find(|w| {
+                            flake.x >= w.x + margin
+                                && flake.x <= w.x + w.width - margin
+                                && prev_bottom <= w.y
+                                && flake_bottom >= w.y
+                        }),
+                        FlakeLayer::Behind => None,
+                    };
+                    let landing = match landing {
+                        Some(_) if !rng.random_bool(self.config.stick_chance as f64) => {
+                            // Glances off the window top and falls on, a little slower
+                            flake.speed *= GLANCE_SLOWDOWN;
+                            flake.vy *= GLANCE_SLOWDOWN;
+                            None
+                        }
+                        landing => landing,
+                    };
 
-                        if *melt_timer >= melt_duration {
-                            flake.reset(state.width, state.height, &state.config, &mut rng);
-                            if !valid_x_ranges.is_empty() {
-                                let range = &valid_x_ranges[rng.random_range(0..valid_x_ranges.len())];
-                                flake.x = rng.random_range(range.0..range.1);
+                    let ground = self.ground.surface_at(flake.x).unwrap_or(self.height);
+                    if let Some(window) = landing {
+                        // Anything sliding off this window later starts again from rest
+                        flake.vy = 0.0;
+                        flake.y = window.y - flake.radius;
+                        flake.state = SnowState::Landed {
+                            melt_timer: 0.0,
+                            melt_duration: melt_time(flake.radius, &self.config),
+                            window_addr: Some(window.address.clone()),
+                            offset_x: flake.x - window.x,
+                            window_width: window.width,
+                        };
+                    } else if flake.y > ground - flake.radius {
+                        flake.y = ground - flake.radius;
+                        self.ground.deposit(flake.x, flake.radius);
+                        flake.state = SnowState::Landed {
+                            melt_timer: 0.0,
+                            melt_duration: melt_time(flake.radius, &self.config),
+                            window_addr: None,
+                            offset_x: 0.0,
+                            window_width: 0.0,
+                        };
+                    }
+                }
+                SnowState::Landed {
+                    melt_timer,
+                    melt_duration,
+                    window_addr,
+                    offset_x,
+                    window_width,
+                } => {
+                    if let Some(addr) = window_addr {
+                        let Some(window) =
+                            self.windows.iter().chain(&self.bars).find(|w| &w.address == addr)
+                        else {
+                            // Only a closed window takes its snow down with it when fading
+                            if self.config.orphan_behavior == OrphanBehavior::Fade
+                                && !self.closed_windows.contains(addr)
+                            {
+                                flake.retiring.get_or_insert(1.0);
+                            } else {
+                                flake.state = SnowState::Falling;
                             }
+                            continue;
+                        };
+
+                        // A resize keeps snow where it is on screen; a move carries it along
+                        if window.width != *window_width {
+                            *offset_x = flake.x - window.x;
+                            *window_width = window.width;
+                        }
+
+                        // Snow left hanging past a shrunken edge slides off that edge
+                        if *offset_x < 0.0 || *offset_x > window.width {
+                            flake.x = if *offset_x < 0.0 {
+                                window.x - flake.radius
+                            } else {
+                                window.x + window.width + flake.radius
+                            };
+                            flake.state = SnowState::Falling;
+                            continue;
+                        }
+                        flake.x = window.x + *offset_x;
+
+                        // A window raised into the snow lifts it; one lowered away drops it
+                        let expected_y = window.y - flake.radius;
+                        if flake.y < expected_y - 1.0 {
+                            flake.state = SnowState::Falling;
+                            continue;
                         }
+                        flake.y = expected_y;
+                    }
+
+                    let warmth = if window_addr.is_some() && *window_addr == self.focused_window {
+                        self.config.focus_melt_multiplier
+                    } else {
+                        1.0
+                    };
+                    *melt_timer += dt * warmth;
+                    let melt_progress = *melt_timer / *melt_duration;
+                    flake.opacity = melt_opacity(self.config.melt_curve, melt_progress)
+                        * self.config.max_opacity;
+
+                    // A flake faded past seeing only costs draw calls, so it's done early
+                    if *melt_timer >= *melt_duration
+                        || flake.opacity < self.config.reset_opacity_threshold
+                    {
+                        if self.wet_marks.len() >= MAX_WET_MARKS {
+                            self.wet_marks.remove(0);
+                        }
+                        self.wet_marks.push((
+                            Point::new(flake.x, flake.y + flake.radius),
+                            WET_MARK_SECONDS,
+                        ));
+                        if draining || flake.retiring.is_some() {
+                            spent.push(i);
+                            continue;
+                        }
+                        flake.reset(self.width, self.height, &self.config, sprite_count, rng);
+                        spawner.place(flake, rng);
                     }
                 }
             }
+        }
+
+        if !spent.is_empty() {
+            let mut i = 0;
+            self.snowflakes.retain(|_| {
+                i += 1;
+                spent.binary_search(&(i - 1)).is_err()
+            });
+        }
+        self.closed_windows.clear();
+    }
+}
+
+#[to_layer_message]
+#[derive(Debug, Clone)]
+pub enum Message {
+    Tick(Instant),
+    Signal(SignalEvent),
+    Control(ControlCommand),
+    /// Keys only arrive with `--interactive`, which gives the overlay the keyboard
+    IntensityUp,
+    IntensityDown,
+    TogglePause,
+}
+
+/// Overlay size assumed when no compositor can be asked for its monitors
+const DEFAULT_WIDTH: f32 = 1920.0;
+const DEFAULT_HEIGHT: f32 = 1080.0;
+
+/// Boot function - initializes the application state. `args` are re-applied on every hot
+/// reload of the config file.
+pub fn boot(config: SnowConfig, args: Args) -> (Waysnow, Task<Message>) {
+    let hyprland_running = std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some();
+    let source: Box<dyn WindowSource> = if hyprland_running {
+        Box::new(HyprlandSource)
+    } else {
+        eprintln!("hyprsnow: Hyprland is not running, snow will not land on windows");
+        Box::new(NullSource::new(DEFAULT_WIDTH, DEFAULT_HEIGHT))
+    };
 
-            state.cache.clear();
+    let mut bounds = source.bounds();
+    // A `region` band only covers part of the screen, and the snow stays within it
+    if config.region > 0 {
+        let height = (config.region as f32).min(bounds.height);
+        if config.anchor == RegionAnchor::Bottom {
+            bounds.y += bounds.height - height;
         }
+        bounds.height = height;
+    }
+    let mut state = Waysnow::new(config, bounds, source);
+    state.config_rx = spawn_config_watcher(args);
+    state.ground.restore();
+
+    (state, Task::none())
+}
+
+/// Update function - handles messages and updates state
+pub fn update(state: &mut Waysnow, message: Message) -> Task<Message> {
+    match message {
+        Message::Tick(now) => {
+            tick(state, now);
+            if state.is_drained() {
+                return exit(state);
+            }
+        }
+        Message::Signal(SignalEvent::TogglePause) => {
+            state.paused = !state.paused;
+        }
+        Message::Signal(SignalEvent::Shutdown) => {
+            // A second signal, or nothing on screen to fade, means exit right away
+            if state.draining.is_some() || state.paused || state.is_idle() {
+                return exit(state);
+            }
+            state.draining = Some(state.last_tick);
+        }
+        Message::Control(command) => control(state, command),
+        Message::IntensityUp => nudge_intensity(state, 1),
+        Message::IntensityDown => nudge_intensity(state, -1),
+        Message::TogglePause => state.paused = !state.paused,
         _ => {}
     }
 
     Task::none()
 }
 
+/// Quits, keeping the ground piles for the next start
+fn exit(state: &Waysnow) -> Task<Message> {
+    state.ground.save();
+    remove_socket();
+    iced::exit()
+}
+
+fn control(state: &mut Waysnow, command: ControlCommand) {
+    match command {
+        ControlCommand::Pause => state.paused = true,
+        ControlCommand::Resume => state.paused = false,
+        ControlCommand::TogglePause => state.paused = !state.paused,
+        ControlCommand::Set { key, value } => {
+            let mut new_config = state.config.clone();
+            match new_config.set(&key, &value) {
+                Ok(()) => {
+                    new_config.fix_ranges();
+                    state.apply_config_change(new_config);
+                }
+                Err(e) => eprintln!("hyprsnow: Ignoring control command: {}", e),
+            }
+        }
+    }
+}
+
+/// Moves the configured intensity `delta` steps within 1-10, as if set over the control socket
+fn nudge_intensity(state: &mut Waysnow, delta: i8) {
+    let intensity = state.config.intensity.saturating_add_signed(delta);
+    let mut new_config = state.config.clone();
+    new_config.intensity = intensity.clamp(1, 10);
+    state.apply_config_change(new_config);
+}
+
+/// Maps the keys of `--interactive` to their messages.
+fn key_message(event: keyboard::Event) -> Option<Message> {
+    let keyboard::Event::KeyPressed { key, .. } = event else {
+        return None;
+    };
+    match key.as_ref() {
+        // `=` shares its key with `+` on most layouts
+        Key::Character("+" | "=") => Some(Message::IntensityUp),
+        Key::Character("-") => Some(Message::IntensityDown),
+        Key::Named(key::Named::Space) => Some(Message::TogglePause),
+        _ => None,
+    }
+}
+
+/// Least time between restarts of a window event listener that stopped
+const LISTENER_RESTART_DELAY: Duration = Duration::from_secs(5);
+
+/// Weight of the newest frame in the `debug` frame rate, which would flicker unsmoothed
+const FRAME_RATE_SMOOTHING: f32 = 0.05;
+
+/// Advances the simulation to `now`
+fn tick(state: &mut Waysnow, now: Instant) {
+    let dt = now.duration_since(state.last_tick).as_secs_f32();
+    state.last_tick = now;
+    if dt > 0.0 {
+        state.frame_rate += (1.0 / dt - state.frame_rate) * FRAME_RATE_SMOOTHING;
+    }
+
+    // Keep the last frame on screen while paused
+    if state.paused {
+        return;
+    }
+
+    // Check for config changes (non-blocking)
+    while let Ok(event) = state.config_rx.try_recv() {
+        match event {
+            ConfigEvent::ConfigChanged(new_config) => state.apply_config_change(*new_config),
+            ConfigEvent::ParseError(e) => eprintln!("hyprsnow: {}, keeping the current config", e),
+        }
+    }
+
+    if now.duration_since(state.last_schedule_check) >= SCHEDULE_STEP {
+        state.last_schedule_check = now;
+        state.follow_schedule();
+    }
+
+    state.poll_source();
+    state.step(dt);
+
+    state.cache.clear();
+    state.behind_cache.clear();
+}
+
 /// View function - renders the UI
 pub fn view(state: &Waysnow) -> Element<'_, Message, Theme, Renderer> {
-    Canvas::new(state)
-        .width(Length::Fill)
-        .height(Length::Fill)
-        .into()
+    let canvas = Canvas::new(state).width(Length::Fill).height(Length::Fill);
+    if !state.config.debug {
+        return canvas.into();
+    }
+
+    // Drawn as a widget rather than on the canvas, so snapshots stay free of it
+    let hud = container(text(state.debug_text()).size(14))
+        .padding(8)
+        .style(|_| container::Style {
+            text_color: Some(Color::WHITE),
+            background: Some(Color::from_rgba(0.0, 0.0, 0.0, 0.5).into()),
+            ..container::Style::default()
+        });
+    stack![canvas, hud].into()
 }
 
 /// Subscription function - sets up event subscriptions
-pub fn subscription(_state: &Waysnow) -> Subscription<Message> {
-    iced::time::every(Duration::from_millis(16)).map(Message::Tick)
+pub fn subscription(state: &Waysnow) -> Subscription<Message> {
+    // Physics is dt-based, so a lower frame rate only makes motion less smooth, not slower
+    let frame_interval = if state.is_idle() {
+        Duration::from_secs(1)
+    } else {
+        Duration::from_millis(1000 / state.config.fps as u64)
+    };
+
+    Subscription::batch([
+        iced::time::every(frame_interval).map(Message::Tick),
+        Subscription::run(signal_stream).map(Message::Signal),
+        Subscription::run(control_stream).map(Message::Control),
+        keyboard::listen().filter_map(key_message),
+    ])
 }
 
 impl canvas::Program<Message> for &Waysnow {
@@ -339,40 +2046,459 @@ impl canvas::Program<Message> for &Waysnow {
         bounds: Rectangle,
         _cursor: Cursor,
     ) -> Vec<Geometry> {
-        let geometry = self.cache.draw(renderer, bounds.size(), |frame: &mut Frame| {
-            for flake in &self.snowflakes {
-                if self.is_in_fullscreen_monitor(flake.x, flake.y) {
-                    continue;
-                }
+        // Behind flakes (and the tint, ground pile and wet marks) are drawn first so front flakes render over them
+        let behind = self.behind_cache.draw(renderer, bounds.size(), |frame: &mut Frame| {
+            self.draw_behind(frame);
+        });
 
-                if let Some(idx) = flake.image_index {
-                    if let Some(handle) = self.cached_images.get(idx) {
-                        let size = flake.radius * 2.0;
-                        frame.draw_image(
-                            Rectangle {
-                                x: flake.x - flake.radius,
-                                y: flake.y - flake.radius,
-                                width: size,
-                                height: size,
-                            },
-                            handle,
-                        );
-                        continue;
-                    }
-                }
+        let front = self.cache.draw(renderer, bounds.size(), |frame: &mut Frame| {
+            self.draw_flakes(frame, FlakeLayer::Front);
+        });
 
-                let color = Color {
-                    r: 1.0,
-                    g: 1.0,
-                    b: 1.0,
-                    a: flake.opacity,
-                };
+        vec![behind, front]
+    }
+}
 
-                let circle = Path::circle(Point::new(flake.x, flake.y), flake.radius);
-                frame.fill(&circle, color);
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: f32 = 800.0;
+    const HEIGHT: f32 = 600.0;
+    const DT: f32 = 1.0 / 60.0;
+
+    fn test_config() -> SnowConfig {
+        SnowConfig {
+            seed: Some(7),
+            count: Some(100),
+            ..SnowConfig::default()
+        }
+    }
+
+    /// A `Waysnow` over an empty `NullSource`, with windows and monitors to be injected by hand
+    fn headless(config: SnowConfig) -> Waysnow {
+        let bounds = Rectangle::with_size(Size::new(WIDTH, HEIGHT));
+        Waysnow::new(config, bounds, Box::new(NullSource::new(WIDTH, HEIGHT)))
+    }
+
+    fn window(address: &str, x: f32, y: f32, width: f32, height: f32) -> WindowRect {
+        WindowRect {
+            address: Address::new(address),
+            class: String::from("test"),
+            title: String::new(),
+            x,
+            y,
+            width,
+            height,
+            floating: false,
+        }
+    }
+
+    /// A source reporting fixed windows and monitors in global layout coordinates
+    struct FixedSource {
+        windows: Vec<WindowRect>,
+        monitors: Vec<MonitorRect>,
+    }
+
+    impl WindowSource for FixedSource {
+        fn bounds(&self) -> Rectangle {
+            Rectangle::with_size(Size::new(WIDTH, HEIGHT))
+        }
+
+        fn windows(&self) -> Vec<WindowRect> {
+            self.windows.clone()
+        }
+
+        fn layer_surfaces(&self) -> Vec<WindowRect> {
+            Vec::new()
+        }
+
+        fn monitors(&self) -> Vec<MonitorRect> {
+            self.monitors.clone()
+        }
+
+        fn active_window(&self) -> Option<Address> {
+            None
+        }
+
+        fn cursor_position(&self) -> Option<(f32, f32)> {
+            None
+        }
+
+        fn events(&mut self) -> mpsc::Receiver<WindowEvent> {
+            mpsc::channel().1
+        }
+    }
+
+    fn monitor(name: &str, x: f32, y: f32, width: f32, height: f32) -> MonitorRect {
+        MonitorRect {
+            name: String::from(name),
+            x,
+            y,
+            width,
+            height,
+            has_fullscreen: false,
+            has_maximized: false,
+        }
+    }
+
+    fn set_windows(state: &mut Waysnow, windows: Vec<WindowRect>) {
+        state.windows = windows;
+        state.refresh_landing_targets();
+    }
+
+    fn is_landed(flake: &Snowflake) -> bool {
+        matches!(flake.state, SnowState::Landed { .. })
+    }
+
+    fn run(state: &mut Waysnow, seconds: f32) {
+        for _ in 0..(seconds / DT).round() as usize {
+            state.step(DT);
+        }
+    }
+
+    /// Replaces the population with a single flake built from `config`
+    fn lone_flake(state: &mut Waysnow) -> &mut Snowflake {
+        let flake = Snowflake::new(WIDTH, HEIGHT, &state.config, 0, &mut state.rng);
+        state.snowflakes = vec![flake];
+        state.target_count = 1;
+        &mut state.snowflakes[0]
+    }
+
+    #[test]
+    fn monitor_intensity_weighs_monitors_by_area() {
+        let mut state = headless(SnowConfig {
+            intensity: 2,
+            monitor_intensity: std::collections::HashMap::from([(String::from("big"), 6)]),
+            ..SnowConfig::default()
+        });
+        state.monitors = vec![
+            monitor("big", 0.0, 0.0, 600.0, 600.0),
+            monitor("small", 600.0, 0.0, 200.0, 600.0),
+        ];
+
+        // Three quarters of the area at intensity 6 and one quarter at 2
+        let expected =
+            0.75 * state.config.flake_count(6) as f32 + 0.25 * state.config.flake_count(2) as f32;
+        assert_eq!(state.wanted_count(), expected.round() as usize);
+    }
+
+    #[test]
+    fn monitor_intensity_sets_the_density_of_spawned_snow() {
+        let mut state = headless(SnowConfig {
+            seed: Some(7),
+            intensity: 2,
+            monitor_intensity: std::collections::HashMap::from([(String::from("big"), 6)]),
+            ..SnowConfig::default()
+        });
+        state.monitors = vec![
+            monitor("big", 0.0, 0.0, 600.0, 600.0),
+            monitor("small", 600.0, 0.0, 200.0, 600.0),
+        ];
+        state.snowflakes.clear();
+        state.spawn_flakes(4000);
+
+        // Intensity 6 over three times the area: nine of every ten flakes
+        let on_big = state.snowflakes.iter().filter(|f| f.x < 600.0).count();
+        let share = on_big as f32 / state.snowflakes.len() as f32;
+        assert!((share - 0.9).abs() < 0.02, "{share}");
+    }
+
+    #[test]
+    fn schedule_sets_the_intensity_of_monitors_without_a_block() {
+        let mut state = headless(SnowConfig {
+            seed: Some(7),
+            intensity: 2,
+            monitor_intensity: std::collections::HashMap::from([(String::from("big"), 2)]),
+            ..SnowConfig::default()
+        });
+        state.monitors = vec![
+            monitor("big", 0.0, 0.0, 600.0, 600.0),
+            monitor("small", 600.0, 0.0, 200.0, 600.0),
+        ];
+        state.scheduled_intensity = Some(6);
+        state.snowflakes.clear();
+        state.spawn_flakes(4000);
+
+        let expected =
+            0.75 * state.config.flake_count(2) as f32 + 0.25 * state.config.flake_count(6) as f32;
+        assert_eq!(state.wanted_count(), expected.round() as usize);
+        // Intensity 2 over three times the area of intensity 6: half the flakes each
+        let on_big = state.snowflakes.iter().filter(|f| f.x < 600.0).count();
+        let share = on_big as f32 / state.snowflakes.len() as f32;
+        assert!((share - 0.5).abs() < 0.02, "{share}");
+    }
+
+    #[test]
+    fn density_counts_only_monitors_that_show_snow() {
+        let mut state = headless(SnowConfig {
+            density_per_megapixel: Some(100.0),
+            monitors: vec![String::from("DP-2")],
+            ..SnowConfig::default()
+        });
+        state.monitors = vec![
+            monitor("DP-1", 0.0, 0.0, 1000.0, 1000.0),
+            monitor("DP-2", 1000.0, 0.0, 1000.0, 1000.0),
+        ];
+        assert_eq!(state.wanted_count(), 100);
+    }
+
+    #[test]
+    fn snow_lands_on_window_tops() {
+        let mut state = headless(test_config());
+        set_windows(&mut state, vec![window("0x1", 0.0, 300.0, WIDTH, 200.0)]);
+        run(&mut state, 10.0);
+
+        let landed: Vec<&Snowflake> = state
+            .snowflakes
+            .iter()
+            .filter(|f| is_landed(f) && f.y < 300.0)
+            .collect();
+        assert!(!landed.is_empty());
+        for flake in landed {
+            assert!((flake.y + flake.radius - 300.0).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn landed_flakes_melt_and_fall_anew() {
+        let mut state = headless(test_config());
+        set_windows(&mut state, vec![window("0x1", 0.0, 300.0, WIDTH, 200.0)]);
+        let flake = lone_flake(&mut state);
+        flake.x = 100.0;
+        flake.y = 300.0 - flake.radius;
+        flake.state = SnowState::Landed {
+            melt_timer: 0.0,
+            melt_duration: 1.0,
+            window_addr: Some(Address::new("0x1")),
+            offset_x: 100.0,
+            window_width: WIDTH,
+        };
+
+        run(&mut state, 0.5);
+        assert!(is_landed(&state.snowflakes[0]));
+        run(&mut state, 1.0);
+        assert!(!is_landed(&state.snowflakes[0]));
+    }
+
+    #[test]
+    fn wind_wraps_flakes_around_the_sides() {
+        let mut state = headless(SnowConfig {
+            wind_x: 300.0,
+            land: false,
+            ..test_config()
+        });
+        run(&mut state, 5.0);
+
+        assert!(state.snowflakes.iter().all(|f| (0.0..WIDTH).contains(&f.x)));
+    }
+
+    #[test]
+    fn windows_are_placed_in_overlay_space() {
+        // A 200px band along the bottom of a 1000px tall monitor
+        let source = FixedSource {
+            windows: vec![window("0x1", 0.0, 900.0, WIDTH, 100.0)],
+            monitors: vec![monitor("DP-1", 0.0, 0.0, WIDTH, 1000.0)],
+        };
+        let bounds = Rectangle::new(Point::new(0.0, 800.0), Size::new(WIDTH, 200.0));
+        let mut state = Waysnow::new(test_config(), bounds, Box::new(source));
+        let target = state.landing_targets.at_x(10.0).next();
+        assert_eq!(target.map(|w| w.y), Some(100.0));
+
+        // Respawns start at the band's top rather than the monitor's, far above it
+        let spawner = Spawner {
+            ranges: state.get_valid_spawn_ranges(),
+            windows: &state.landing_targets,
+            avoid: None,
+            bias: 0.0,
+            width: state.width,
+            height: state.height,
+        };
+        let flake = &mut state.snowflakes[0];
+        spawner.place_at_top(flake, &mut StdRng::seed_from_u64(1));
+        assert!(flake.y >= -flake.radius);
+
+        run(&mut state, 10.0);
+        assert!(state.snowflakes.iter().any(|f| is_landed(f) && f.y < 100.0));
+    }
+
+    #[test]
+    fn windows_on_a_monitor_above_the_origin_catch_snow() {
+        let source = FixedSource {
+            windows: vec![
+                window("0x1", 0.0, -500.0, WIDTH, 200.0),
+                window("0x2", 0.0, 300.0, WIDTH, 200.0),
+            ],
+            monitors: vec![
+                monitor("DP-1", 0.0, -HEIGHT, WIDTH, HEIGHT),
+                monitor("DP-2", 0.0, 0.0, WIDTH, HEIGHT),
+            ],
+        };
+        let bounds = Rectangle::new(Point::new(0.0, -HEIGHT), Size::new(WIDTH, 2.0 * HEIGHT));
+        let state = Waysnow::new(test_config(), bounds, Box::new(source));
+
+        let tops: Vec<f32> = state.landing_targets.at_x(10.0).map(|w| w.y).collect();
+        assert_eq!(tops, [100.0, 900.0]);
+    }
+
+    #[test]
+    fn fullscreen_hides_only_its_own_monitor_in_a_vertical_stack() {
+        let source = FixedSource {
+            windows: Vec::new(),
+            monitors: vec![
+                monitor("DP-1", 0.0, 0.0, WIDTH, HEIGHT),
+                MonitorRect {
+                    has_fullscreen: true,
+                    ..monitor("DP-2", 0.0, HEIGHT, WIDTH, HEIGHT)
+                },
+            ],
+        };
+        let bounds = Rectangle::with_size(Size::new(WIDTH, 2.0 * HEIGHT));
+        let state = Waysnow::new(test_config(), bounds, Box::new(source));
+
+        assert!(!state.is_hidden_at(100.0, HEIGHT / 2.0));
+        assert!(state.is_hidden_at(100.0, HEIGHT * 1.5));
+    }
+
+    #[test]
+    fn drift_stays_smooth_over_long_runs() {
+        // A hovering flake, so it never respawns and gets a fresh phase
+        let mut state = headless(SnowConfig {
+            drift: 20.0,
+            speed_min: 0.0,
+            speed_max: 0.0,
+            gravity: 0.0,
+            twinkle: true,
+            ..test_config()
+        });
+        let flake = lone_flake(&mut state);
+        let (amount, freq) = (flake.drift_amount, flake.drift_freq);
+        // The most vx and the twinkle can change in one tick while the sway is continuous
+        let max_vx_step = amount * freq * DT * 1.01 + 1e-3;
+        let max_twinkle_step = (1.0 - TWINKLE_MIN) * 0.5 * TWINKLE_RATE * freq * DT * 1.01 + 1e-4;
+
+        state.step(DT);
+        let mut prev = (state.snowflakes[0].vx, twinkle_factor(&state.snowflakes[0]));
+        // An hour at 60 fps
+        for _ in 0..216_000 {
+            state.step(DT);
+            let flake = &state.snowflakes[0];
+            assert!((0.0..std::f32::consts::TAU).contains(&flake.phase));
+            assert!(flake.vx.abs() <= amount + 1e-3);
+            assert!((flake.vx - prev.0).abs() <= max_vx_step);
+            let twinkle = twinkle_factor(flake);
+            assert!((twinkle - prev.1).abs() <= max_twinkle_step);
+            prev = (flake.vx, twinkle);
+        }
+    }
+
+    #[test]
+    fn zero_drift_falls_straight_down() {
+        let mut state = headless(SnowConfig {
+            drift: 0.0,
+            spin_min: 0.0,
+            spin_max: 0.0,
+            max_lifetime: 0.5,
+            land: false,
+            ..test_config()
+        });
+        // Long enough for every flake to respawn a few times
+        run(&mut state, 2.0);
+
+        for flake in &state.snowflakes {
+            assert_eq!(flake.drift_amount, 0.0);
+            assert_eq!(flake.vx, 0.0);
+        }
+    }
+
+    #[test]
+    fn no_flake_is_drawn_above_max_opacity() {
+        let mut state = headless(SnowConfig {
+            twinkle: true,
+            ..test_config()
+        });
+        set_windows(&mut state, vec![window("0x1", 0.0, 300.0, WIDTH, 200.0)]);
+        run(&mut state, 10.0);
+        assert!(state.snowflakes.iter().any(is_landed));
+        assert!(state.snowflakes.iter().any(|f| !is_landed(f)));
+
+        let dimmed = SnowConfig {
+            max_opacity: 0.3,
+            ..state.config.clone()
+        };
+        state.apply_config_change(dimmed);
+        // Landed flakes pick the new ceiling up on their next melt step
+        state.step(DT);
+        for flake in &state.snowflakes {
+            assert!(state.drawn_opacity(flake, 1.0) <= 0.3 + 1e-6);
+        }
+    }
+
+    #[test]
+    fn bigger_flakes_melt_slower() {
+        let mut state = headless(SnowConfig {
+            size_min: 1.0,
+            size_max: 6.0,
+            melt_duration: 4.0,
+            melt_size_variation: 0.5,
+            ..test_config()
+        });
+        set_windows(&mut state, vec![window("0x1", 0.0, 300.0, WIDTH, 200.0)]);
+        let mut small = Snowflake::new(WIDTH, HEIGHT, &state.config, 0, &mut state.rng);
+        let mut big = Snowflake::new(WIDTH, HEIGHT, &state.config, 0, &mut state.rng);
+        for (flake, radius, x) in [(&mut small, 1.5, 100.0), (&mut big, 5.5, 400.0)] {
+            flake.radius = radius;
+            flake.x = x;
+            flake.y = 300.0 - radius;
+            flake.state = SnowState::Landed {
+                melt_timer: 0.0,
+                melt_duration: melt_time(radius, &state.config),
+                window_addr: Some(Address::new("0x1")),
+                offset_x: x,
+                window_width: WIDTH,
+            };
+        }
+        state.snowflakes = vec![small, big];
+        state.target_count = 2;
+
+        run(&mut state, 2.0);
+        let [small, big] = &state.snowflakes[..] else {
+            panic!("expected both flakes to still be there");
+        };
+        assert!(is_landed(small) && is_landed(big));
+        assert!(big.opacity > small.opacity);
+    }
+
+    #[test]
+    fn equal_speed_bounds_give_a_constant_speed() {
+        let mut state = headless(SnowConfig {
+            speed_min: 50.0,
+            speed_max: 50.0,
+            parallax: false,
+            max_lifetime: 0.5,
+            ..test_config()
+        });
+        run(&mut state, 2.0);
+
+        assert!(state.snowflakes.iter().all(|f| f.speed == 50.0));
+    }
+
+    #[test]
+    fn rising_flakes_respawn_after_leaving_the_top() {
+        let mut state = headless(SnowConfig {
+            speed_min: -40.0,
+            speed_max: -40.0,
+            gravity: 0.0,
+            parallax: false,
+            ..test_config()
         });
+        let flake = lone_flake(&mut state);
+        flake.y = 10.0;
 
-        vec![geometry]
+        // Gone over the top after about half a second
+        run(&mut state, 2.0);
+        let flake = &state.snowflakes[0];
+        assert!(flake.age < 2.0);
+        assert!(flake.y + flake.radius >= 0.0);
     }
 }
@@ -0,0 +1,106 @@
+use crate::hyprland::{MonitorRect, WindowRect};
+use hyprland::shared::Address;
+use iced::{Rectangle, Size};
+use std::sync::mpsc;
+
+/// Changes in the window layout, sent by a `WindowSource` so the overlay knows when to refetch.
+#[derive(Debug, Clone)]
+pub enum WindowEvent {
+    WindowOpened,
+    /// The window with this address was closed, rather than just leaving the screen
+    WindowClosed(Address),
+    /// A window was moved to another workspace
+    WindowMoved,
+    /// Focus moved to the window with this address, or to no window at all
+    ActiveWindowChanged(Option<Address>),
+    WorkspaceChanged,
+    FullscreenChanged,
+    /// A special (scratchpad) workspace was shown or hidden on a monitor
+    SpecialWorkspaceChanged,
+    /// The event stream closed, e.g. because the compositor restarted
+    Disconnected,
+    /// The event stream is back after a `Disconnected`
+    Reconnected,
+}
+
+/// Where the overlay learns about monitors and windows. Everything is in global layout
+/// coordinates; Hyprland is the only compositor backend so far.
+pub trait WindowSource {
+    /// The box spanning all monitors, which the overlay covers
+    fn bounds(&self) -> Rectangle;
+
+    /// Windows currently on screen
+    fn windows(&self) -> Vec<WindowRect>;
+
+    /// Bars, docks and other layer-shell surfaces snow may land on
+    fn layer_surfaces(&self) -> Vec<WindowRect>;
+
+    fn monitors(&self) -> Vec<MonitorRect>;
+
+    fn active_window(&self) -> Option<Address>;
+
+    /// Global pointer position; the overlay ignores input, so it can't track the mouse itself
+    fn cursor_position(&self) -> Option<(f32, f32)>;
+
+    /// Starts watching the compositor; layout changes arrive on the returned channel.
+    fn events(&mut self) -> mpsc::Receiver<WindowEvent>;
+}
+
+/// A source without a compositor: one `width`x`height` monitor at the origin and no windows.
+pub struct NullSource {
+    width: f32,
+    height: f32,
+    /// Held so the event channel stays open, as a closed one means the listener died
+    events: Option<mpsc::Sender<WindowEvent>>,
+}
+
+impl NullSource {
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            width,
+            height,
+            events: None,
+        }
+    }
+}
+
+impl WindowSource for NullSource {
+    fn bounds(&self) -> Rectangle {
+        Rectangle::with_size(Size::new(self.width, self.height))
+    }
+
+    fn windows(&self) -> Vec<WindowRect> {
+        Vec::new()
+    }
+
+    fn layer_surfaces(&self) -> Vec<WindowRect> {
+        Vec::new()
+    }
+
+    fn monitors(&self) -> Vec<MonitorRect> {
+        vec![MonitorRect {
+            name: String::from("default"),
+            x: 0.0,
+            y: 0.0,
+            width: self.width,
+            height: self.height,
+            has_fullscreen: false,
+            has_maximized: false,
+        }]
+    }
+
+    fn active_window(&self) -> Option<Address> {
+        None
+    }
+
+    fn cursor_position(&self) -> Option<(f32, f32)> {
+        None
+    }
+
+    fn events(&mut self) -> mpsc::Receiver<WindowEvent> {
+        // Nothing ever changes, so nothing is ever sent
+        let (tx, rx) = mpsc::channel();
+        self.events = Some(tx);
+        rx
+    }
+}
@@ -0,0 +1,76 @@
+use rand::Rng;
+
+/// Global wind shared by every falling flake, made of a steady base velocity plus occasional
+/// gusts that swell and die down again.
+pub struct Wind {
+    base_x: f32,
+    base_y: f32,
+    gust_strength: f32,
+    gust_peak: f32,
+    gust_elapsed: f32,
+    gust_duration: f32,
+    next_gust_in: f32,
+}
+
+impl Wind {
+    pub fn new(base_x: f32, base_y: f32, gust_strength: f32, rng: &mut impl Rng) -> Self {
+        Self {
+            base_x,
+            base_y,
+            gust_strength,
+            gust_peak: 0.0,
+            gust_elapsed: 0.0,
+            gust_duration: 0.0,
+            next_gust_in: rng.random_range(2.0..8.0),
+        }
+    }
+
+    pub fn set_strength(&mut self, base_x: f32, base_y: f32, gust_strength: f32) {
+        self.base_x = base_x;
+        self.base_y = base_y;
+        self.gust_strength = gust_strength;
+    }
+
+    /// Current wind velocity `(x, y)` in pixels/second.
+    pub fn speed(&self) -> (f32, f32) {
+        // Gusts blow along the base wind so the breeze reads as one coherent direction
+        let length = self.base_x.hypot(self.base_y);
+        let (dir_x, dir_y) = if length > 0.0 {
+            (self.base_x / length, self.base_y / length)
+        } else {
+            (1.0, 0.0)
+        };
+        let gust = self.gust();
+        (self.base_x + dir_x * gust, self.base_y + dir_y * gust)
+    }
+
+    fn gust(&self) -> f32 {
+        if self.gust_elapsed >= self.gust_duration {
+            return 0.0;
+        }
+        // Half a sine wave: ramps up to the peak, then decays back to calm
+        let progress = self.gust_elapsed / self.gust_duration;
+        (progress * std::f32::consts::PI).sin() * self.gust_peak
+    }
+
+    pub fn update(&mut self, dt: f32, rng: &mut impl Rng) {
+        if self.gust_elapsed < self.gust_duration {
+            self.gust_elapsed += dt;
+            return;
+        }
+
+        self.next_gust_in -= dt;
+        if self.next_gust_in > 0.0 {
+            return;
+        }
+
+        self.next_gust_in = rng.random_range(4.0..12.0);
+        if self.gust_strength <= 0.0 {
+            return;
+        }
+
+        self.gust_peak = self.gust_strength * rng.random_range(0.5..1.0);
+        self.gust_duration = rng.random_range(1.5..4.0);
+        self.gust_elapsed = 0.0;
+    }
+}